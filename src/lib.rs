@@ -1,45 +1,487 @@
-use image::imageops;
-use std::path::PathBuf;
-use std::process::Command;
+use image::{ imageops::FilterType, ImageFormat };
+use std::{ fs, path::PathBuf };
 use serde::{ Serialize, Deserialize };
 
 pub mod utils {
+  pub mod animation;
+  pub mod audio;
+  pub mod black_bars;
+  pub mod cancel;
+  pub mod color;
+  pub mod coordinates;
+  pub mod dimensions;
+  pub mod encoding;
+  pub mod ffmpeg;
   pub mod file;
+  pub mod format;
+  pub mod frames;
+  pub mod hls;
+  pub mod keyframes;
+  pub mod limits;
+  pub mod overlay;
+  pub mod poster;
+  pub mod probe;
+  pub mod progress;
+  pub mod reframe;
+  pub mod scenes;
+  pub mod streams;
+  pub mod region;
+  pub mod thumbnails;
+  pub mod trim;
   pub mod validation;
 }
-use utils::file::file::get_output_path;
-use utils::validation::validation::validate_options;
+use utils::file::file::{ append_to_file_name, get_output_path };
+use utils::overlay::overlay::apply_watermark;
+use utils::color::color::prepare_for_format;
+use utils::dimensions::dimensions::{
+  apply_dimension_policy,
+  apply_dimension_policy_to_keyframes,
+  get_output_size,
+  get_padding_filter,
+};
+use utils::coordinates::coordinates::{ get_square_pixel_filter, map_keyframes_to_frame, map_to_frame };
+use utils::reframe::reframe::auto_reframe;
+use utils::scenes::scenes::find_scene_changes;
+use utils::region::region::open_region;
+use utils::keyframes::keyframes::get_moving_crop_filter;
+use utils::limits::limits::{ open_image, ImageLimits };
+use utils::animation::animation::{ get_animation_args, get_animation_extension, get_animation_filter };
+use utils::audio::audio::{ get_audio_args, get_default_audio_args };
+use utils::black_bars::black_bars::find_black_bars;
+use utils::cancel::cancel::CancelToken;
+use utils::encoding::encoding::{ get_container, get_encoding_args };
+use utils::ffmpeg::ffmpeg::run_ffmpeg;
+use utils::poster::poster::{ find_sharpest_frame, DEFAULT_POSTER_FORMAT };
+use utils::probe::probe::probe_video;
+use utils::progress::progress::{ ProgressParser, VideoProgress };
+use utils::streams::streams::get_stream_args;
+use utils::hls::hls::package_hls;
+use utils::thumbnails::thumbnails::create_thumbnails;
+use utils::trim::trim::{ copy_trimmed, is_full_frame };
+use utils::validation::validation::{
+  validate_black_bar_input,
+  validate_crop_options,
+  validate_options,
+  validate_poster_options,
+  validate_scene_options,
+};
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy)]
 pub struct Point {
   pub x: u32,
   pub y: u32,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy)]
 pub struct ImageSize {
   pub width: u32,
   pub height: u32,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy)]
 pub struct CropDimensions {
   pub top_left_point: Point,
   pub size: ImageSize,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+/// How the crop moves from a keyframe to the next one.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Interpolation {
+  #[default]
+  Linear,
+  /// Starts and ends slowly (smoothstep).
+  Ease,
+  /// Stays in place until the next keyframe.
+  Hold,
+}
+
+/// Aspect ratio of automatically reframed videos.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReframeAspect {
+  #[serde(rename = "9:16")]
+  Vertical,
+  #[serde(rename = "1:1")]
+  Square,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum AnimationFormat {
+  #[default]
+  Gif,
+  Webp,
+}
+
+/// Converts the cropped video to an animated image.
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+pub struct AnimationOptions {
+  pub format: AnimationFormat,
+  /// Frames per second. Defaults to 12.
+  pub frame_rate: Option<f64>,
+  /// Wider animations are scaled down to this width (px). Defaults to 480.
+  pub max_width: Option<u32>,
+  /// Number of times the animation is repeated, 0 means forever. Defaults to 0.
+  pub loop_count: Option<u32>,
+}
+
+/// Position of a moving video crop at the given time.
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+pub struct CropKeyframe {
+  /// Time in seconds, relative to the start of the input video.
+  pub time: f64,
+  pub top_left_point: Point,
+  /// Interpolation towards the next keyframe. Defaults to `linear`.
+  pub interpolation: Option<Interpolation>,
+}
+
+/// Where the watermark is anchored on the cropped image.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Gravity {
+  TopLeft,
+  Top,
+  TopRight,
+  Left,
+  Center,
+  Right,
+  BottomLeft,
+  Bottom,
+  #[default]
+  BottomRight,
+}
+
+/// How odd crop dimensions are made even for video encoders.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum DimensionPolicy {
+  /// Drops the last odd row or column.
+  #[default]
+  RoundDown,
+  /// Adds a row or column of the video.
+  RoundUp,
+  /// Pads the crop with a black row or column.
+  Pad,
+}
+
+/// How videos that are only trimmed (and not cropped) are cut.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum TrimMode {
+  /// Starts at the last keyframe before the start time. Nothing is re-encoded,
+  /// unless there is no keyframe within 60 seconds before the start time.
+  #[default]
+  Keyframe,
+  /// Starts exactly at the start time. Only the frames before the first keyframe are re-encoded.
+  Accurate,
+}
+
+/// Video codecs that can be chosen for the cropped video.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum VideoCodec {
+  H264,
+  H265,
+  Vp9,
+  Av1,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+pub struct EncodingProfile {
+  /// Defaults to h264 (vp9 for webm).
+  pub codec: Option<VideoCodec>,
+  /// Constant rate factor, lower values mean better quality. Can't be combined with `bitrate`.
+  pub crf: Option<u32>,
+  /// Target bitrate in kbit/s.
+  pub bitrate: Option<u32>,
+  /// Speed preset, e.g. "medium" for h264/h265 or a number for vp9/av1.
+  pub preset: Option<String>,
+  /// Pixel format, e.g. "yuv420p".
+  pub pixel_format: Option<String>,
+  /// Extension of the output container, e.g. "mkv".
+  /// Defaults to the input container, or mp4 if the input container is not mp4, mkv, mov or webm (e.g. avi).
+  pub container: Option<String>,
+}
+
+/// What happens with the audio of the cropped video.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AudioMode {
+  Copy,
+  Encode,
+  Remove,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AudioCodec {
+  Aac,
+  Opus,
+  Mp3,
+  Vorbis,
+  Flac,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+pub struct AudioOptions {
+  /// Defaults to `encode` if the codec, bitrate or loudness is set, `copy` otherwise.
+  pub mode: Option<AudioMode>,
+  /// Defaults to aac (opus for webm).
+  pub codec: Option<AudioCodec>,
+  /// Bitrate in kbit/s.
+  pub bitrate: Option<u32>,
+  /// Indexes of the audio tracks that are kept (starting at 0). Defaults to all tracks.
+  pub tracks: Option<Vec<u32>>,
+  /// Target integrated loudness in LUFS, e.g. -16.
+  pub loudness: Option<f64>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+pub struct ThumbnailOptions {
+  /// Seconds between the thumbnails. Defaults to 2.
+  pub interval: Option<f64>,
+  /// Width of a thumbnail (px), the height keeps the aspect ratio. Defaults to 160.
+  pub width: Option<u32>,
+  /// Thumbnails in a row of the sprite. Defaults to 10.
+  pub columns: Option<u32>,
+  /// Reference to the sprite in the WebVTT file. Defaults to the file name of the sprite.
+  pub sprite_url: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+pub struct ThumbnailFiles {
+  /// Sprite sheet (JPEG) with the thumbnails in rows.
+  pub sprite_file_path: String,
+  /// WebVTT file that maps the time ranges to the thumbnails of the sprite.
+  pub vtt_file_path: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+pub struct HlsRendition {
+  /// Height of the rendition (px), the width keeps the aspect ratio.
+  pub height: u32,
+  /// Video bitrate in kbit/s. Defaults to the ffmpeg default.
+  pub bitrate: Option<u32>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+pub struct HlsOptions {
+  /// Renditions of the ladder. Defaults to 1080p, 720p, 480p and 360p, skipping the ones taller than the video.
+  pub renditions: Option<Vec<HlsRendition>>,
+  /// Target length of the segments in seconds. Defaults to 6.
+  pub segment_duration: Option<f64>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+pub struct HlsFiles {
+  pub master_playlist_path: String,
+  /// All files of the package - the master playlist, the rendition playlists and the segments.
+  pub file_paths: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct Watermark {
+  /// Path of the watermark inside the storage bucket.
+  pub storage_file_path: Option<String>,
+  /// Local path of the watermark file.
+  pub file_path: Option<String>,
+  /// Anchor of the watermark, ignored when `position` is set. Defaults to `bottom_right`.
+  pub gravity: Option<Gravity>,
+  /// Exact top left point of the watermark on the cropped image.
+  pub position: Option<Point>,
+  /// Value between 0 and 1. Defaults to 1.
+  pub opacity: Option<f32>,
+  /// Watermark width relative to the cropped image width (0 - 1]. Defaults to 0.2.
+  pub scale: Option<f32>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
 pub struct CropParameters {
   pub input_file_path: String,
   pub output_file_path: Option<String>,
-  pub dimensions: CropDimensions,
+  /// Crop of the input. Defaults to the whole image or (displayed) video frame.
+  pub dimensions: Option<CropDimensions>,
+  pub watermark: Option<Watermark>,
+  /// Size that the cropped image is scaled to.
+  pub resize: Option<ImageSize>,
+  /// Extension of the output image format, e.g. "png". Defaults to the input format.
+  pub format: Option<String>,
+  /// Image decoding limits. Defaults to `ImageLimits::from_env()`.
+  pub limits: Option<ImageLimits>,
+  /// Start of the trimmed video in seconds.
+  pub start: Option<f64>,
+  /// End of the trimmed video in seconds. Can't be combined with `duration`.
+  pub end: Option<f64>,
+  /// Length of the trimmed video in seconds.
+  pub duration: Option<f64>,
+  /// Cut points used when the video is trimmed without cropping. Defaults to `TrimMode::Keyframe`.
+  pub trim_mode: Option<TrimMode>,
+  /// Codec and quality of the cropped video. Defaults to the ffmpeg defaults for the container.
+  pub encoding: Option<EncodingProfile>,
+  /// Audio tracks and codec of the cropped video.
+  /// Defaults to all tracks, copied unless the container can't store their codec.
+  pub audio: Option<AudioOptions>,
+  /// Moves the video crop over time. The size of the crop is always `dimensions.size`.
+  pub keyframes: Option<Vec<CropKeyframe>>,
+  /// Finds the subject and crops the video to the aspect ratio, `dimensions` are ignored.
+  pub auto_reframe: Option<ReframeAspect>,
+  /// Outputs an animated GIF or WebP instead of a video.
+  pub animation: Option<AnimationOptions>,
+  /// Creates a sprite sheet and a WebVTT file for previews on the timeline of the cropped video.
+  pub thumbnails: Option<ThumbnailOptions>,
+  /// Packages the cropped video for HLS streaming, in a folder next to the video.
+  pub hls: Option<HlsOptions>,
+  /// How odd video crop dimensions are made even. Defaults to `DimensionPolicy::RoundDown`.
+  pub dimension_policy: Option<DimensionPolicy>,
+  /// Keeps the container metadata of the video, e.g. the title and creation time. Defaults to `true`.
+  pub keep_metadata: Option<bool>,
+  /// Keeps the chapters of the video. Defaults to `true`.
+  pub keep_chapters: Option<bool>,
+  /// Detects the black bars of the video and crops them away, `dimensions` are ignored. Defaults to `false`.
+  pub remove_black_bars: Option<bool>,
+}
+
+impl CropParameters {
+  /// Returns the crop on an input of the given size, which is the whole input by default.
+  ///
+  /// ## Usage:
+  /// ```
+  /// use drag_and_crop::CropParameters;
+  ///
+  /// let params = CropParameters::default();
+  /// let dimensions = params.get_dimensions((1920, 1080));
+  /// assert_eq!((dimensions.top_left_point.x, dimensions.size.width, dimensions.size.height), (0, 1920, 1080));
+  /// ```
+  pub fn get_dimensions(&self, (width, height): (u32, u32)) -> CropDimensions {
+    self.dimensions.unwrap_or(CropDimensions { top_left_point: Point { x: 0, y: 0 }, size: ImageSize { width, height } })
+  }
+
+  /// Returns `true` if only a part of the video should be kept.
+  pub fn is_trimmed(&self) -> bool {
+    self.start.is_some() || self.end.is_some() || self.duration.is_some()
+  }
+
+  /// Returns the length of the trimmed video, if it's limited by the end time or duration.
+  ///
+  /// ## Usage:
+  /// ```
+  /// use drag_and_crop::CropParameters;
+  ///
+  /// let params = CropParameters { start: Some(5.0), end: Some(15.0), ..Default::default() };
+  /// assert_eq!(params.get_trim_duration(), Some(10.0));
+  ///
+  /// let params = CropParameters { start: Some(5.0), ..Default::default() };
+  /// assert_eq!(params.get_trim_duration(), None);
+  /// ```
+  pub fn get_trim_duration(&self) -> Option<f64> {
+    match (self.end, self.duration) {
+      (Some(end), _) => Some(end - self.start.unwrap_or(0.0)),
+      (None, Some(duration)) => Some(duration),
+      (None, None) => None,
+    }
+  }
 }
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct CropRequest {
   pub storage_file_path: Option<String>,
   pub url: Option<String>,
-  pub dimensions: CropDimensions,
+  pub dimensions: Option<CropDimensions>,
+  pub watermark: Option<Watermark>,
+  pub resize: Option<ImageSize>,
+  pub format: Option<String>,
+  pub start: Option<f64>,
+  pub end: Option<f64>,
+  pub duration: Option<f64>,
+  pub trim_mode: Option<TrimMode>,
+  pub encoding: Option<EncodingProfile>,
+  pub audio: Option<AudioOptions>,
+  pub keyframes: Option<Vec<CropKeyframe>>,
+  pub auto_reframe: Option<ReframeAspect>,
+  pub animation: Option<AnimationOptions>,
+  pub thumbnails: Option<ThumbnailOptions>,
+  pub hls: Option<HlsOptions>,
+  pub dimension_policy: Option<DimensionPolicy>,
+  pub keep_metadata: Option<bool>,
+  pub keep_chapters: Option<bool>,
+  pub remove_black_bars: Option<bool>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct CropResult {
+  pub file_path: String,
+  /// Lossy conversions that couldn't be avoided, e.g. reducing the bit depth.
+  pub warnings: Vec<String>,
+  /// Actual start of the trimmed video (or the time of the poster frame) in seconds.
+  pub start: Option<f64>,
+  /// Actual end of the trimmed video in seconds.
+  pub end: Option<f64>,
+  /// Thumbnails of the cropped video, if they were requested.
+  pub thumbnails: Option<ThumbnailFiles>,
+  /// HLS package of the cropped video, if it was requested.
+  pub hls: Option<HlsFiles>,
+  /// Size of the cropped video, after the dimension policy was applied.
+  pub size: Option<ImageSize>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct PosterParameters {
+  pub input_file_path: String,
+  pub output_file_path: Option<String>,
+  /// Time of the frame in seconds. Defaults to the sharpest frame of the video.
+  pub time: Option<f64>,
+  /// Crop of the frame. Defaults to the whole frame.
+  pub dimensions: Option<CropDimensions>,
+  /// Extension of the output image format, e.g. "png". Defaults to "jpg".
+  pub format: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct PosterRequest {
+  pub storage_file_path: Option<String>,
+  pub url: Option<String>,
+  pub time: Option<f64>,
+  pub dimensions: Option<CropDimensions>,
+  pub format: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct SceneParameters {
+  pub input_file_path: String,
+  /// Path that the thumbnail paths are derived from, e.g. "video.jpg" gives "video-scene-001.jpg".
+  pub output_file_path: Option<String>,
+  /// Minimum change between two frames (0 - 1) that counts as a cut. Defaults to 0.4.
+  pub threshold: Option<f64>,
+  /// Width of the thumbnails (px), the height keeps the aspect ratio. Defaults to 160.
+  pub thumbnail_width: Option<u32>,
+  /// Maximum number of detected cuts. Defaults to 100.
+  pub max_scenes: Option<u32>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SceneRequest {
+  pub storage_file_path: Option<String>,
+  pub url: Option<String>,
+  pub threshold: Option<f64>,
+  pub thumbnail_width: Option<u32>,
+  pub max_scenes: Option<u32>,
+}
+
+/// Cut between two scenes of a video.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SceneBoundary {
+  /// Time of the first frame of the new scene in seconds.
+  pub time: f64,
+  /// Change from the previous frame (0 - 1).
+  pub score: f64,
+  /// Thumbnail of the first frame of the new scene (the storage path in API responses).
+  pub thumbnail: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct BlackBarRequest {
+  pub storage_file_path: Option<String>,
+  pub url: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -54,92 +496,438 @@ pub struct ApiResponse<T> {
   pub data: Option<T>,
 }
 
+/// Data of a successful video crop. Image crops only return the storage path.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct VideoCropResponse {
+  /// Storage path of the cropped video, animation or HLS master playlist.
+  pub storage_file_path: String,
+  /// Actual start of the trimmed video in seconds.
+  pub start: Option<f64>,
+  /// Actual end of the trimmed video in seconds.
+  pub end: Option<f64>,
+  /// Size of the cropped video, after the dimension policy was applied.
+  pub size: Option<ImageSize>,
+  /// Storage paths of the sprite sheet and the WebVTT file, if thumbnails were requested.
+  pub thumbnails: Option<ThumbnailFiles>,
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct UploadResponse {
   pub name: String,
 }
 
+/// Version and capabilities of the detected ffmpeg, without the paths of the binaries.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct FfmpegCapabilities {
+  pub version: String,
+  pub ffprobe_version: String,
+  pub video_encoders: Vec<String>,
+  pub audio_encoders: Vec<String>,
+  pub filters: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct HealthResponse {
+  /// Detected ffmpeg installation, `None` if video processing is unavailable.
+  pub ffmpeg: Option<FfmpegCapabilities>,
+}
+
 /**
  * Crops image with the given options and returns the file path of the newly created cropped image.
+ * The color type and bit depth of the input image are kept whenever the output format supports them.
  */
-pub fn crop_image(options: &CropParameters) -> Result<String, String> {
-  validate_options(&options)?;
+pub fn crop_image(options: &CropParameters) -> Result<CropResult, String> {
+  validate_options(options)?;
+  // the whole image is kept by default, only the header is read for its size
+  let dimensions = match options.dimensions {
+    Some(dimensions) => dimensions,
+    None =>
+      match image::image_dimensions(&options.input_file_path) {
+        Ok(size) => options.get_dimensions(size),
+        Err(error) => {
+          return Err(error.to_string());
+        }
+      }
+  };
 
-  let mut img = match image::open(&options.input_file_path) {
-    Ok(img) => img,
-    Err(error) => {
-      return Err(format!("There was an error while opening the image file: {:?}", error));
+  // decode only the cropped region when the format allows it
+  let limits = options.limits.unwrap_or_else(ImageLimits::from_env);
+  let region = open_region(
+    &options.input_file_path,
+    &dimensions,
+    options.resize.as_ref(),
+    &limits
+  )?;
+  let mut result_img = match region {
+    Some(img) => img,
+    None => {
+      let img = open_image(&options.input_file_path, &limits)?;
+      img.crop_imm(
+        dimensions.top_left_point.x,
+        dimensions.top_left_point.y,
+        dimensions.size.width,
+        dimensions.size.height
+      )
     }
   };
-  let cropped_img = imageops::crop(
-    &mut img,
-    options.dimensions.top_left_point.x,
-    options.dimensions.top_left_point.y,
-    options.dimensions.size.width,
-    options.dimensions.size.height
-  );
+  if let Some(size) = &options.resize {
+    result_img = result_img.resize_exact(size.width, size.height, FilterType::Lanczos3);
+  }
+  if let Some(watermark) = &options.watermark {
+    result_img = apply_watermark(result_img, watermark, &limits)?;
+  }
 
-  let output_file_path;
+  let mut output_file_path;
   if let Some(output_path) = &options.output_file_path {
     output_file_path = PathBuf::from(&output_path);
   } else {
     output_file_path = get_output_path(&options.input_file_path);
   }
-  match cropped_img.to_image().save(&output_file_path) {
+  if let Some(format) = &options.format {
+    output_file_path.set_extension(format.to_lowercase());
+  }
+
+  let mut warnings = Vec::new();
+  if let Ok(format) = ImageFormat::from_path(&output_file_path) {
+    let (converted_img, warning) = prepare_for_format(result_img, format);
+    result_img = converted_img;
+    warnings.extend(warning);
+  }
+
+  match result_img.save(&output_file_path) {
     Ok(_) => (),
     Err(error) => {
       return Err(format!("There was an error while saving the cropped image: {:?}", error));
     }
   }
 
-  Ok(output_file_path.to_str().unwrap().to_string())
+  Ok(CropResult {
+    file_path: output_file_path.to_str().unwrap().to_string(),
+    warnings,
+    ..Default::default()
+  })
 }
 
 /**
  * Crops video with the given options and returns the file path of the newly created cropped video file.
  */
-pub fn crop_video(options: &CropParameters) -> Result<String, String> {
-  validate_options(&options)?;
-
-  let crop_dimensions = format!(
-    "crop={}:{}:{}:{}",
-    &options.dimensions.size.width,
-    &options.dimensions.size.height,
-    &options.dimensions.top_left_point.x,
-    &options.dimensions.top_left_point.y
-  ); // width:height:x:y
-  let output_file_path;
+pub fn crop_video(options: &CropParameters) -> Result<CropResult, String> {
+  crop_video_with_progress(options, &CancelToken::new(), |_| ())
+}
+
+/**
+ * Crops video like `crop_video` and calls `on_progress` whenever ffmpeg reports its progress.
+ * If the job is cancelled, ffmpeg is killed and the partial output is deleted.
+ */
+pub fn crop_video_with_progress(
+  options: &CropParameters,
+  cancel: &CancelToken,
+  mut on_progress: impl FnMut(&VideoProgress)
+) -> Result<CropResult, String> {
+  // the video is probed once, by the validation
+  let video = match validate_crop_options(options, cancel)? {
+    Some(video) => video,
+    None => {
+      return Err(String::from("The input file is not a valid video."));
+    }
+  };
+
+  let mut output_file_path;
   if let Some(output_path) = &options.output_file_path {
     output_file_path = PathBuf::from(&output_path);
   } else {
     output_file_path = get_output_path(&options.input_file_path);
   }
+  let start = options.start.unwrap_or(0.0);
+  // the black bars are detected in the trimmed part of the video, the crop is then handled like a requested one
+  let dimensions = if options.remove_black_bars == Some(true) {
+    find_black_bars(
+      &options.input_file_path,
+      &video,
+      (options.start, options.get_trim_duration()),
+      &append_to_file_name(&output_file_path.with_extension("txt"), "-black-bars"),
+      cancel
+    )?
+  } else {
+    options.get_dimensions(video.get_display_size())
+  };
+  // encoders of chroma-subsampled video require even dimensions, animations are scaled anyway
+  let policy = if options.animation.is_none() { Some(options.dimension_policy.unwrap_or_default()) } else { None };
+  // the crop is given on the displayed frame, ffmpeg crops the (rotated) frame with the coded pixels
+  let (crop_size, mut crop_dimensions) = match (&options.auto_reframe, &options.keyframes) {
+    (Some(aspect), _) => {
+      // the reframed size is always even
+      let (size, keyframes) = auto_reframe(options, &video, *aspect, &output_file_path, cancel)?;
+      let (frame_size, frame_keyframes) = map_keyframes_to_frame(&size, &keyframes, &video);
+      (size, get_moving_crop_filter(&frame_size, &frame_keyframes, start))
+    }
+    (None, Some(keyframes)) => {
+      let (size, keyframes) = match policy {
+        Some(policy) =>
+          apply_dimension_policy_to_keyframes(
+            &dimensions.size,
+            keyframes,
+            policy,
+            video.get_display_size()
+          )?,
+        None => (dimensions.size, keyframes.clone()),
+      };
+      let (frame_size, frame_keyframes) = map_keyframes_to_frame(&size, &keyframes, &video);
+      (size, get_moving_crop_filter(&frame_size, &frame_keyframes, start))
+    }
+    (None, None) => {
+      let dimensions = match policy {
+        Some(policy) => apply_dimension_policy(&dimensions, policy, video.get_display_size())?,
+        None => dimensions,
+      };
+      let frame_dimensions = map_to_frame(&dimensions, &video);
+      let filter = format!(
+        "crop={}:{}:{}:{}",
+        frame_dimensions.size.width,
+        frame_dimensions.size.height,
+        frame_dimensions.top_left_point.x,
+        frame_dimensions.top_left_point.y
+      ); // width:height:x:y
+      (dimensions.size, filter)
+    }
+  };
+  // anamorphic videos are scaled to square pixels
+  if let Some(filter) = get_square_pixel_filter(&crop_size, &video) {
+    crop_dimensions = format!("{},{}", crop_dimensions, filter);
+  }
+  if let Some(filter) = policy.and_then(|policy| get_padding_filter(&crop_size, policy)) {
+    crop_dimensions = format!("{},{}", crop_dimensions, filter);
+  }
+  if let Some(animation) = &options.animation {
+    crop_dimensions = format!("{},{}", crop_dimensions, get_animation_filter(animation));
+  }
+  let mut encoding_args = Vec::new();
+  if let Some(encoding) = &options.encoding {
+    encoding_args = get_encoding_args(encoding, &options.input_file_path)?;
+    output_file_path.set_extension(get_container(Some(encoding), &options.input_file_path));
+  }
+  match &options.audio {
+    Some(audio) => encoding_args.extend(get_audio_args(audio, options)?),
+    // animations don't have audio
+    None if options.animation.is_none() => {
+      let container = get_container(options.encoding.as_ref(), &options.input_file_path);
+      encoding_args.extend(get_default_audio_args(&video, &container)?);
+    }
+    None => (),
+  }
+  if let Some(animation) = &options.animation {
+    encoding_args.extend(get_animation_args(animation)?);
+    output_file_path.set_extension(get_animation_extension(animation.format));
+  }
+  // the streams are mapped explicitly, animations only have the video stream
+  let (stream_args, warnings) = if options.animation.is_none() {
+    get_stream_args(options, &video, &get_container(options.encoding.as_ref(), &options.input_file_path))?
+  } else {
+    (Vec::new(), Vec::new())
+  };
 
-  let output = Command::new("ffmpeg.exe")
-    .arg("-i")
-    .arg(&options.input_file_path)
-    .arg("-filter:v")
-    .arg(&crop_dimensions)
-    .arg(&output_file_path)
-    .arg("-loglevel")
-    .arg("error")
-    .output();
+  // videos that are only trimmed don't have to be re-encoded
+  let is_trim_only =
+    options.is_trimmed() && is_full_frame(&dimensions, video.get_display_size());
+  let is_reencoded =
+    !video.has_square_pixels() ||
+    options.encoding.is_some() ||
+    options.audio.is_some() ||
+    options.keyframes.is_some() ||
+    options.auto_reframe.is_some() ||
+    options.animation.is_some();
+  if is_trim_only && !is_reencoded {
+    match copy_trimmed(options, &video, &stream_args, &output_file_path, cancel, &mut on_progress) {
+      Ok(Some((start, end))) => {
+        let (width, height) = video.get_display_size();
+        let result = CropResult {
+          file_path: output_file_path.to_string_lossy().to_string(),
+          warnings,
+          start: Some(start),
+          end: Some(end),
+          size: Some(ImageSize { width, height }),
+          ..Default::default()
+        };
+        return add_video_outputs(result, options, cancel);
+      }
+      Ok(None) => (),
+      Err(error) => {
+        let _ = fs::remove_file(&output_file_path);
+        return Err(error);
+      }
+    }
+  }
 
-  let status = match output {
-    Ok(ref output) => output.status,
-    Err(error) => {
-      return Err(format!("There was an error while processing the video: {}", error));
+  // trimming and cropping are done in a single pass
+  let mut args = Vec::new();
+  if let Some(start) = options.start {
+    args.extend([String::from("-ss"), start.to_string()]);
+  }
+  args.extend([String::from("-i"), options.input_file_path.clone()]);
+  if let Some(duration) = options.get_trim_duration() {
+    args.extend([String::from("-t"), duration.to_string()]);
+  }
+  args.extend([String::from("-filter:v"), crop_dimensions]);
+  args.extend(stream_args);
+  args.extend(encoding_args);
+  args.push(output_file_path.to_string_lossy().to_string());
+
+  // the progress is relative to the trimmed part of the video
+  let output_duration = options
+    .get_trim_duration()
+    .or_else(|| video.duration.map(|duration| duration - options.start.unwrap_or(0.0)));
+  let frame_count = if options.is_trimmed() { None } else { video.frame_count };
+  let parser = ProgressParser::new(output_duration, frame_count);
+  if let Err(error) = run_ffmpeg(&args, parser, cancel, on_progress) {
+    // the output didn't exist before (checked by the validation), so only the partial output is deleted
+    let _ = fs::remove_file(&output_file_path);
+    return Err(error);
+  }
+
+  let (start, end) = if options.is_trimmed() {
+    let start = options.start.unwrap_or(0.0);
+    (Some(start), output_duration.map(|duration| start + duration))
+  } else {
+    (None, None)
+  };
+  let result = CropResult {
+    file_path: output_file_path.to_string_lossy().to_string(),
+    warnings,
+    start,
+    end,
+    // animations are scaled to their maximum width
+    size: policy.map(|policy| get_output_size(&crop_size, policy)),
+    ..Default::default()
+  };
+  add_video_outputs(result, options, cancel)
+}
+
+/// Creates the requested thumbnails and HLS package of the cropped video.
+/// The video is deleted if they can't be created, so that the crop can be retried.
+fn add_video_outputs(
+  mut result: CropResult,
+  options: &CropParameters,
+  cancel: &CancelToken
+) -> Result<CropResult, String> {
+  let video_file_path = PathBuf::from(&result.file_path);
+  if let Some(thumbnail_options) = &options.thumbnails {
+    match create_thumbnails(&video_file_path, thumbnail_options, cancel) {
+      Ok(thumbnails) => {
+        result.thumbnails = Some(thumbnails);
+      }
+      Err(error) => {
+        let _ = fs::remove_file(&video_file_path);
+        return Err(error);
+      }
+    }
+  }
+  if let Some(hls_options) = &options.hls {
+    match package_hls(&video_file_path, hls_options, cancel) {
+      Ok(hls) => {
+        result.hls = Some(hls);
+      }
+      Err(error) => {
+        let _ = fs::remove_file(&video_file_path);
+        if let Some(thumbnails) = &result.thumbnails {
+          let _ = fs::remove_file(&thumbnails.sprite_file_path);
+          let _ = fs::remove_file(&thumbnails.vtt_file_path);
+        }
+        return Err(error);
+      }
+    }
+  }
+
+  Ok(result)
+}
+
+/**
+ * Extracts a still image (e.g. a cover image) from the video and returns the file path of the cropped image.
+ * The frame at `time` is used, or the sharpest frame of the video if no time is given.
+ */
+pub fn extract_poster(options: &PosterParameters, cancel: &CancelToken) -> Result<CropResult, String> {
+  validate_poster_options(options)?;
+  let video = probe_video(&options.input_file_path, cancel)?;
+
+  let mut output_file_path;
+  if let Some(output_path) = &options.output_file_path {
+    output_file_path = PathBuf::from(&output_path);
+  } else {
+    output_file_path = get_output_path(&options.input_file_path);
+  }
+  let format = options.format.as_deref().unwrap_or(DEFAULT_POSTER_FORMAT).to_lowercase();
+  output_file_path.set_extension(&format);
+
+  // 1) find the frame
+  let time = match options.time {
+    Some(time) => time,
+    None => {
+      let frames_path = append_to_file_name(&output_file_path.with_extension("gray"), "-frames");
+      find_sharpest_frame(&options.input_file_path, &video, &frames_path, cancel)?
     }
   };
 
-  if !status.success() {
-    let error_message = format!(
-      "{}\n{}",
-      status.to_string(),
-      std::str::from_utf8(&output.unwrap().stderr).unwrap()
-    );
-    return Err(error_message);
+  // 2) extract the frame losslessly, as it's displayed (rotated, with square pixels)
+  let frame_file_path = append_to_file_name(&output_file_path.with_extension("png"), "-frame");
+  let (width, height) = video.get_display_size();
+  let mut args = vec![
+    String::from("-ss"),
+    time.to_string(),
+    String::from("-i"),
+    options.input_file_path.clone(),
+    String::from("-frames:v"),
+    String::from("1"),
+    String::from("-an"),
+  ];
+  if let Some(filter) = get_square_pixel_filter(&(ImageSize { width, height }), &video) {
+    args.extend([String::from("-filter:v"), filter]);
   }
+  args.push(frame_file_path.to_string_lossy().to_string());
+  if let Err(error) = run_ffmpeg(&args, ProgressParser::new(None, None), cancel, |_| ()) {
+    let _ = fs::remove_file(&frame_file_path);
+    return Err(error);
+  }
+  if !frame_file_path.exists() {
+    return Err(format!("The frame at {}s could not be extracted.", time));
+  }
+
+  // 3) crop and convert the frame like any other image
+  let result = crop_image(
+    &(CropParameters {
+      input_file_path: frame_file_path.to_string_lossy().to_string(),
+      output_file_path: Some(output_file_path.to_string_lossy().to_string()),
+      dimensions: options.dimensions,
+      ..Default::default()
+    })
+  );
+  let _ = fs::remove_file(&frame_file_path);
+
+  Ok(CropResult { start: Some(time), ..result? })
+}
+
+/**
+ * Finds the cuts between the scenes of the video and extracts a thumbnail of the first frame of each new scene.
+ * The thumbnails are saved next to the output file path and removed if the detection fails.
+ */
+pub fn detect_scenes(options: &SceneParameters, cancel: &CancelToken) -> Result<Vec<SceneBoundary>, String> {
+  validate_scene_options(options)?;
+  let video = probe_video(&options.input_file_path, cancel)?;
+
+  let output_file_path = match &options.output_file_path {
+    Some(output_path) => PathBuf::from(output_path),
+    None => get_output_path(&options.input_file_path),
+  };
+  find_scene_changes(options, &video, &output_file_path.with_extension("jpg"), cancel)
+}
+
+/**
+ * Finds the black bars of a letterboxed or pillarboxed video and returns the crop without them,
+ * on the displayed frame. The whole frame is returned if the video has no black bars.
+ */
+pub fn detect_black_bars(input_file_path: &str, cancel: &CancelToken) -> Result<CropDimensions, String> {
+  validate_black_bar_input(input_file_path)?;
+  let video = probe_video(input_file_path, cancel)?;
 
-  return Ok(output_file_path.to_string_lossy().to_string());
+  let output_file_path = get_output_path(&input_file_path.to_string());
+  let metadata_path = append_to_file_name(&output_file_path.with_extension("txt"), "-black-bars");
+  find_black_bars(input_file_path, &video, (None, None), &metadata_path, cancel)
 }
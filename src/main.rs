@@ -7,6 +7,7 @@ pub mod web {
   pub mod firebase;
   pub mod url;
   pub mod handlers;
+  pub mod jobs;
 }
 pub mod utils {
   pub mod init;
@@ -15,16 +16,28 @@ pub mod utils {
 use utils::init::init;
 use utils::cors::CORS;
 use rocket::fs::{ FileServer, Options };
+use rocket::fairing::AdHoc;
 use std::net::Ipv4Addr;
 use rocket::Config;
+use drag_and_crop::utils::ffmpeg::ffmpeg::{ init_ffmpeg, FfmpegConfig };
 use crate::web::endpoints::routes::{
+  get_health,
   post_crop_image,
   post_crop_video,
+  post_cancel_video_job,
+  post_extract_poster,
+  post_detect_scenes,
+  post_detect_black_bars,
   post_upload_media,
   options_crop_image,
   options_crop_video,
+  options_cancel_video_job,
+  options_extract_poster,
+  options_detect_scenes,
+  options_detect_black_bars,
   options_upload_media,
 };
+use crate::web::jobs::jobs::VideoJobs;
 use crate::web::catchers::{ default_catcher, unprocessable_entity };
 
 #[launch]
@@ -40,6 +53,31 @@ fn rocket() -> _ {
     ::build()
     .configure(config)
     .attach(CORS)
+    .manage(VideoJobs::default())
+    .attach(
+      AdHoc::try_on_ignite("ffmpeg", |rocket| async {
+        // "ffmpeg_path", "ffprobe_path" and "ffmpeg_optional" can be set in Rocket.toml or with ROCKET_ environment variables
+        let config = Config::figment().extract::<FfmpegConfig>().unwrap_or_default();
+        match init_ffmpeg(&config) {
+          Ok(ffmpeg) => {
+            info!("Using ffmpeg {} ({})", ffmpeg.version, ffmpeg.ffmpeg_path);
+            Ok(rocket)
+          }
+          Err(error) if config.ffmpeg_optional == Some(true) => {
+            // images are cropped without ffmpeg, the video endpoints report the stored detection error
+            warn!("Video processing is unavailable, it requires ffmpeg and ffprobe.");
+            warn_!("{}", error);
+            Ok(rocket)
+          }
+          Err(error) => {
+            error!("Video processing requires ffmpeg and ffprobe.");
+            error_!("{}", error);
+            Err(rocket)
+          }
+        }
+      })
+    )
+    .mount("/", routes![get_health])
     .mount("/", FileServer::new("./static/swagger-ui", Options::Index).rank(1))
     .mount("/docs", FileServer::new("./docs", Options::Index).rank(2))
     .mount(
@@ -47,9 +85,17 @@ fn rocket() -> _ {
       routes![
         post_crop_image,
         post_crop_video,
+        post_cancel_video_job,
+        post_extract_poster,
+        post_detect_scenes,
+        post_detect_black_bars,
         post_upload_media,
         options_crop_image,
         options_crop_video,
+        options_cancel_video_job,
+        options_extract_poster,
+        options_detect_scenes,
+        options_detect_black_bars,
         options_upload_media
       ]
     )
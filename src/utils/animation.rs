@@ -0,0 +1,98 @@
+pub mod animation {
+  use crate::{ AnimationFormat, AnimationOptions };
+  use crate::utils::ffmpeg::ffmpeg::get_ffmpeg;
+
+  pub const DEFAULT_FRAME_RATE: f64 = 12.0;
+  pub const DEFAULT_MAX_WIDTH: u32 = 480;
+  /// Higher frame rates aren't played back correctly by most GIF viewers.
+  const MAX_FRAME_RATE: f64 = 50.0;
+  /// Quality of the lossy animated WebP (0 - 100).
+  const WEBP_QUALITY: &str = "75";
+
+  /// Returns the extension of the animation format.
+  pub fn get_animation_extension(format: AnimationFormat) -> &'static str {
+    match format {
+      AnimationFormat::Gif => "gif",
+      AnimationFormat::Webp => "webp",
+    }
+  }
+
+  /// Returns the filters that are applied after the crop.
+  /// GIFs get their own palette, generated from the whole animation.
+  ///
+  /// ## Usage:
+  /// ```
+  /// use drag_and_crop::utils::animation::animation::get_animation_filter;
+  /// use drag_and_crop::{ AnimationFormat, AnimationOptions };
+  ///
+  /// let options = AnimationOptions { format: AnimationFormat::Webp, frame_rate: Some(15.0), ..Default::default() };
+  /// assert_eq!(get_animation_filter(&options), "fps=15,scale='min(iw,480)':-2:flags=lanczos");
+  /// ```
+  pub fn get_animation_filter(options: &AnimationOptions) -> String {
+    let frame_rate = options.frame_rate.unwrap_or(DEFAULT_FRAME_RATE);
+    let max_width = options.max_width.unwrap_or(DEFAULT_MAX_WIDTH);
+    // only downscaled, the height keeps the aspect ratio
+    let filter = format!("fps={},scale='min(iw,{})':-2:flags=lanczos", frame_rate, max_width);
+    match options.format {
+      AnimationFormat::Gif =>
+        format!(
+          "{},split[frames][palette_frames];[palette_frames]palettegen=stats_mode=diff[palette];[frames][palette]paletteuse=dither=bayer:bayer_scale=5:diff_mode=rectangle",
+          filter
+        ),
+      AnimationFormat::Webp => filter,
+    }
+  }
+
+  /// Validates the options against the detected ffmpeg and returns the ffmpeg output arguments.
+  pub fn get_animation_args(options: &AnimationOptions) -> Result<Vec<String>, String> {
+    if let Some(frame_rate) = options.frame_rate {
+      if !(frame_rate > 0.0 && frame_rate <= MAX_FRAME_RATE) {
+        return Err(format!("The frame rate must be between 0 (exclusive) and {}.", MAX_FRAME_RATE));
+      }
+    }
+    if let Some(max_width) = options.max_width {
+      if max_width < 2 {
+        return Err(String::from("The maximum width must be at least 2px."));
+      }
+    }
+
+    let ffmpeg = get_ffmpeg()?;
+    let loop_count = options.loop_count.unwrap_or(0).to_string();
+    match options.format {
+      AnimationFormat::Gif => {
+        for filter in ["palettegen", "paletteuse"] {
+          if !ffmpeg.filters.iter().any(|name| name == filter) {
+            return Err(format!("The {} filter is not supported by the installed ffmpeg.", filter));
+          }
+        }
+        Ok(vec![String::from("-an"), String::from("-loop"), loop_count])
+      }
+      AnimationFormat::Webp => {
+        let encoder = ["libwebp_anim", "libwebp"]
+          .iter()
+          .find(|encoder| ffmpeg.video_encoders.iter().any(|name| name == *encoder));
+        let encoder = match encoder {
+          Some(encoder) => *encoder,
+          None => {
+            return Err(String::from("Animated WebP is not supported by the installed ffmpeg."));
+          }
+        };
+        Ok(
+          [
+            "-an",
+            "-c:v",
+            encoder,
+            "-lossless",
+            "0",
+            "-q:v",
+            WEBP_QUALITY,
+            "-loop",
+            &loop_count,
+          ]
+            .map(String::from)
+            .to_vec()
+        )
+      }
+    }
+  }
+}
@@ -0,0 +1,197 @@
+pub mod audio {
+  use crate::{ AudioCodec, AudioMode, AudioOptions, CropParameters };
+  use crate::utils::encoding::encoding::get_container;
+  use crate::utils::ffmpeg::ffmpeg::get_ffmpeg;
+  use crate::utils::probe::probe::{ AudioTrack, VideoInfo };
+
+  /// Loudness targets outside of this range (in LUFS) are rejected by the loudnorm filter.
+  const LOUDNESS_RANGE: std::ops::RangeInclusive<f64> = -70.0..=-5.0;
+  /// loudnorm resamples to 192 kHz, so the output is resampled to a common rate.
+  const NORMALIZED_SAMPLE_RATE: &str = "48000";
+
+  /// Returns the encoder of the audio codec.
+  pub fn get_audio_encoder(codec: AudioCodec) -> &'static str {
+    match codec {
+      AudioCodec::Aac => "aac",
+      AudioCodec::Opus => "libopus",
+      AudioCodec::Mp3 => "libmp3lame",
+      AudioCodec::Vorbis => "libvorbis",
+      AudioCodec::Flac => "flac",
+    }
+  }
+
+  /// Returns `true` if the container can store the audio codec.
+  ///
+  /// ## Usage:
+  /// ```
+  /// use drag_and_crop::utils::audio::audio::is_supported_audio_container;
+  /// use drag_and_crop::AudioCodec;
+  ///
+  /// assert!(is_supported_audio_container(AudioCodec::Opus, "webm"));
+  /// assert!(!is_supported_audio_container(AudioCodec::Aac, "webm"));
+  /// assert!(is_supported_audio_container(AudioCodec::Flac, "mkv"));
+  /// ```
+  pub fn is_supported_audio_container(codec: AudioCodec, container: &str) -> bool {
+    match container {
+      "mkv" => true,
+      "webm" => matches!(codec, AudioCodec::Opus | AudioCodec::Vorbis),
+      "mp4" => codec != AudioCodec::Vorbis,
+      _ => matches!(codec, AudioCodec::Aac | AudioCodec::Mp3),
+    }
+  }
+
+  /// Returns `true` if the container can store the audio stream (by its ffprobe codec name) without re-encoding it.
+  ///
+  /// ## Usage:
+  /// ```
+  /// use drag_and_crop::utils::audio::audio::is_copyable_audio_codec;
+  ///
+  /// assert!(is_copyable_audio_codec("aac", "mp4"));
+  /// assert!(!is_copyable_audio_codec("aac", "webm"));
+  /// assert!(is_copyable_audio_codec("pcm_s16le", "mov"));
+  /// assert!(is_copyable_audio_codec("dts", "mkv"));
+  /// ```
+  pub fn is_copyable_audio_codec(codec_name: &str, container: &str) -> bool {
+    match container {
+      "mkv" => true,
+      "webm" => matches!(codec_name, "opus" | "vorbis"),
+      "mp4" => matches!(codec_name, "aac" | "mp3" | "opus" | "flac" | "alac" | "ac3" | "eac3"),
+      "mov" => matches!(codec_name, "aac" | "mp3" | "alac" | "ac3" | "eac3") || codec_name.starts_with("pcm_"),
+      "avi" => matches!(codec_name, "aac" | "mp3" | "ac3") || codec_name.starts_with("pcm_"),
+      "flv" => matches!(codec_name, "aac" | "mp3"),
+      "wmv" => matches!(codec_name, "mp3" | "wmav1" | "wmav2"),
+      _ => false,
+    }
+  }
+
+  /// Returns the audio codec that is used when the audio is encoded without a codec.
+  fn get_default_audio_codec(container: &str) -> AudioCodec {
+    if container == "webm" { AudioCodec::Opus } else { AudioCodec::Aac }
+  }
+
+  /// Returns the encoder of the codec, if the container can store it and the installed ffmpeg supports it.
+  fn get_supported_audio_encoder(codec: AudioCodec, container: &str) -> Result<&'static str, String> {
+    let encoder = get_audio_encoder(codec);
+    if !is_supported_audio_container(codec, container) {
+      return Err(format!("The {} audio encoder can't be used with the {} container.", encoder, container));
+    }
+    let ffmpeg = get_ffmpeg()?;
+    if !ffmpeg.audio_encoders.iter().any(|name| name == encoder) {
+      return Err(format!("The {} audio encoder is not supported by the installed ffmpeg.", encoder));
+    }
+    Ok(encoder)
+  }
+
+  /// Returns the ffmpeg output arguments of the audio if no audio options are given.
+  /// The tracks are copied, unless the container can't store their codec - those are encoded with the default codec.
+  pub fn get_default_audio_args(video: &VideoInfo, container: &str) -> Result<Vec<String>, String> {
+    let tracks = &video.audio_tracks;
+    let is_copyable = |track: &AudioTrack| {
+      track.codec_name.as_deref().is_some_and(|codec_name| is_copyable_audio_codec(codec_name, container))
+    };
+    if tracks.iter().all(is_copyable) {
+      return Ok(vec![String::from("-c:a"), String::from("copy")]);
+    }
+
+    // all tracks are mapped, so the output tracks have the same indexes
+    let encoder = get_supported_audio_encoder(get_default_audio_codec(container), container)?;
+    let mut args = Vec::new();
+    for track in tracks {
+      let codec = if is_copyable(track) { "copy" } else { encoder };
+      args.extend([format!("-c:a:{}", track.index), String::from(codec)]);
+    }
+    Ok(args)
+  }
+
+  /// Returns the audio mode - `encode` if the codec, bitrate or loudness is set, `copy` otherwise.
+  pub fn get_audio_mode(audio: &AudioOptions) -> AudioMode {
+    let is_encoded = audio.codec.is_some() || audio.bitrate.is_some() || audio.loudness.is_some();
+    audio.mode.unwrap_or(if is_encoded { AudioMode::Encode } else { AudioMode::Copy })
+  }
+
+  /// Returns the ffmpeg arguments that map the selected audio tracks, or all of them by default.
+  pub fn get_audio_map_args(audio: Option<&AudioOptions>, video: &VideoInfo) -> Result<Vec<String>, String> {
+    let tracks = match audio {
+      Some(audio) if get_audio_mode(audio) == AudioMode::Remove => {
+        return Ok(Vec::new());
+      }
+      Some(audio) => audio.tracks.as_ref(),
+      None => None,
+    };
+    match tracks {
+      Some(tracks) => {
+        if tracks.is_empty() {
+          return Err(
+            String::from("At least one audio track has to be selected, or the audio mode set to \"remove\".")
+          );
+        }
+        let track_count = video.audio_tracks.len() as u32;
+        let mut args = Vec::new();
+        for track in tracks {
+          if *track >= track_count {
+            return Err(
+              format!("The audio track {} does not exist, the video has {} audio tracks.", track, track_count)
+            );
+          }
+          args.extend([String::from("-map"), format!("0:a:{}", track)]);
+        }
+        Ok(args)
+      }
+      // "?" keeps videos without audio from failing
+      None => Ok(vec![String::from("-map"), String::from("0:a?")]),
+    }
+  }
+
+  /// Validates the audio options and returns the ffmpeg output arguments.
+  /// The tracks are mapped by `get_audio_map_args`.
+  pub fn get_audio_args(audio: &AudioOptions, options: &CropParameters) -> Result<Vec<String>, String> {
+    let is_encoded = audio.codec.is_some() || audio.bitrate.is_some() || audio.loudness.is_some();
+    let mode = get_audio_mode(audio);
+    if mode == AudioMode::Remove {
+      if is_encoded || audio.tracks.is_some() {
+        return Err(String::from("The removed audio can't be encoded or filtered."));
+      }
+      return Ok(vec![String::from("-an")]);
+    }
+
+    if mode == AudioMode::Copy {
+      if is_encoded {
+        return Err(String::from("Copied audio can't be re-encoded or normalized."));
+      }
+      return Ok(vec![String::from("-c:a"), String::from("copy")]);
+    }
+
+    // 1) codec
+    let container = get_container(options.encoding.as_ref(), &options.input_file_path);
+    let codec = audio.codec.unwrap_or(get_default_audio_codec(&container));
+    let encoder = get_supported_audio_encoder(codec, &container)?;
+    let mut args = vec![String::from("-c:a"), String::from(encoder)];
+    if let Some(bitrate) = audio.bitrate {
+      if bitrate == 0 {
+        return Err(String::from("The audio bitrate must be larger than 0."));
+      }
+      args.extend([String::from("-b:a"), format!("{}k", bitrate)]);
+    }
+
+    // 2) loudness normalization (single pass)
+    if let Some(loudness) = audio.loudness {
+      if !LOUDNESS_RANGE.contains(&loudness) {
+        return Err(
+          format!(
+            "The loudness must be between {} and {} LUFS.",
+            LOUDNESS_RANGE.start(),
+            LOUDNESS_RANGE.end()
+          )
+        );
+      }
+      args.extend([
+        String::from("-filter:a"),
+        format!("loudnorm=I={}:TP=-1.5:LRA=11", loudness),
+        String::from("-ar"),
+        String::from(NORMALIZED_SAMPLE_RATE),
+      ]);
+    }
+
+    Ok(args)
+  }
+}
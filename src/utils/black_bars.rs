@@ -0,0 +1,158 @@
+pub mod black_bars {
+  use std::{ fs, path::Path };
+
+  use crate::{ CropDimensions, ImageSize, Point };
+  use crate::utils::cancel::cancel::CancelToken;
+  use crate::utils::coordinates::coordinates::get_square_pixel_filter;
+  use crate::utils::ffmpeg::ffmpeg::run_ffmpeg;
+  use crate::utils::probe::probe::VideoInfo;
+  use crate::utils::progress::progress::ProgressParser;
+
+  const MAX_SAMPLE_RATE: f64 = 1.0;
+  const MAX_SAMPLES: u32 = 120;
+  /// Share of the samples whose content may be cut off, so that single bright frames
+  /// (e.g. flashes or credits over the bars) don't widen the crop.
+  const OUTLIER_SHARE: f64 = 0.1;
+
+  /// Rectangle with content in a sampled frame, from `(x1, y1)` to `(x2, y2)` (inclusive).
+  pub type ContentRectangle = (u32, u32, u32, u32);
+
+  /// Parses the rectangles that `cropdetect` found from the output of `metadata=print`.
+  /// Frames without content (e.g. black frames) are skipped.
+  ///
+  /// ## Usage:
+  /// ```
+  /// use drag_and_crop::utils::black_bars::black_bars::parse_content_rectangles;
+  ///
+  /// let output = "frame:0    pts:0       pts_time:0
+  /// lavfi.cropdetect.x1=0
+  /// lavfi.cropdetect.x2=1919
+  /// lavfi.cropdetect.y1=140
+  /// lavfi.cropdetect.y2=939
+  /// frame:1    pts:30030   pts_time:1.001
+  /// lavfi.cropdetect.x1=1919
+  /// lavfi.cropdetect.x2=0
+  /// lavfi.cropdetect.y1=1079
+  /// lavfi.cropdetect.y2=0";
+  /// assert_eq!(parse_content_rectangles(output), vec![(0, 140, 1919, 939)]);
+  /// ```
+  pub fn parse_content_rectangles(output: &str) -> Vec<ContentRectangle> {
+    let mut rectangles = Vec::new();
+    let mut edges: [Option<i64>; 4] = [None; 4];
+    let mut add_rectangle = |edges: &mut [Option<i64>; 4]| {
+      if let [Some(x1), Some(y1), Some(x2), Some(y2)] = *edges {
+        if x1 >= 0 && y1 >= 0 && x2 > x1 && y2 > y1 {
+          rectangles.push((x1 as u32, y1 as u32, x2 as u32, y2 as u32));
+        }
+      }
+      *edges = [None; 4];
+    };
+    for line in output.lines() {
+      let line = line.trim();
+      if line.starts_with("frame:") {
+        add_rectangle(&mut edges);
+      } else if let Some((key, value)) = line.strip_prefix("lavfi.cropdetect.").and_then(|entry| entry.split_once('=')) {
+        let index = match key {
+          "x1" => 0,
+          "y1" => 1,
+          "x2" => 2,
+          "y2" => 3,
+          _ => {
+            continue;
+          }
+        };
+        edges[index] = value.parse().ok();
+      }
+    }
+    add_rectangle(&mut edges);
+    rectangles
+  }
+
+  /// Aggregates the rectangles of the samples into a stable crop of the frame.
+  /// Every edge ignores the outermost 10% of the samples, the whole frame is returned if there are none.
+  ///
+  /// ## Usage:
+  /// ```
+  /// use drag_and_crop::utils::black_bars::black_bars::get_stable_crop;
+  ///
+  /// // letterboxed video with a single bright frame
+  /// let mut rectangles = vec![(0, 140, 1919, 939); 19];
+  /// rectangles.push((0, 0, 1919, 1079));
+  /// let crop = get_stable_crop(&rectangles, (1920, 1080));
+  /// assert_eq!((crop.top_left_point.x, crop.top_left_point.y), (0, 140));
+  /// assert_eq!((crop.size.width, crop.size.height), (1920, 800));
+  ///
+  /// let crop = get_stable_crop(&[], (1920, 1080));
+  /// assert_eq!((crop.size.width, crop.size.height), (1920, 1080));
+  /// ```
+  pub fn get_stable_crop(rectangles: &[ContentRectangle], (width, height): (u32, u32)) -> CropDimensions {
+    if rectangles.is_empty() || width == 0 || height == 0 {
+      return CropDimensions { top_left_point: Point { x: 0, y: 0 }, size: ImageSize { width, height } };
+    }
+    let get_edge = |edge: fn(&ContentRectangle) -> u32, share: f64| {
+      let mut values: Vec<u32> = rectangles.iter().map(edge).collect();
+      values.sort_unstable();
+      values[(((values.len() - 1) as f64) * share).round() as usize]
+    };
+    let x1 = get_edge(|rectangle| rectangle.0, OUTLIER_SHARE).min(width - 1);
+    let y1 = get_edge(|rectangle| rectangle.1, OUTLIER_SHARE).min(height - 1);
+    let x2 = get_edge(|rectangle| rectangle.2, 1.0 - OUTLIER_SHARE).clamp(x1, width - 1);
+    let y2 = get_edge(|rectangle| rectangle.3, 1.0 - OUTLIER_SHARE).clamp(y1, height - 1);
+
+    CropDimensions {
+      top_left_point: Point { x: x1, y: y1 },
+      size: ImageSize { width: x2 - x1 + 1, height: y2 - y1 + 1 },
+    }
+  }
+
+  /// Samples the (trimmed) video with `cropdetect` and returns the crop without the black bars,
+  /// on the displayed frame. The metadata of the samples is written to `metadata_path`, which is removed afterwards.
+  pub fn find_black_bars(
+    input_file_path: &str,
+    video: &VideoInfo,
+    (start, duration): (Option<f64>, Option<f64>),
+    metadata_path: &Path,
+    cancel: &CancelToken
+  ) -> Result<CropDimensions, String> {
+    let (width, height) = video.get_display_size();
+    let sample_rate = match duration.or_else(|| video.duration.map(|duration| duration - start.unwrap_or(0.0))) {
+      Some(duration) if duration > 0.0 => MAX_SAMPLE_RATE.min((MAX_SAMPLES as f64) / duration),
+      _ => MAX_SAMPLE_RATE,
+    };
+
+    // the samples are scaled to square pixels, so the bars are found on the displayed frame
+    let mut filter = format!("fps={}", sample_rate);
+    if let Some(square_pixel_filter) = get_square_pixel_filter(&(ImageSize { width, height }), video) {
+      filter = format!("{},{}", filter, square_pixel_filter);
+    }
+    // the colons of the path are escaped for the option parser and the filter graph parser
+    let metadata_file = metadata_path.to_string_lossy().replace('\\', "/").replace(':', "\\\\:");
+    filter = format!("{},cropdetect=round=2:reset=1,metadata=print:file={}", filter, metadata_file);
+
+    let mut args = Vec::new();
+    if let Some(start) = start {
+      args.extend([String::from("-ss"), start.to_string()]);
+    }
+    args.extend([String::from("-i"), String::from(input_file_path)]);
+    if let Some(duration) = duration {
+      args.extend([String::from("-t"), duration.to_string()]);
+    }
+    args.extend([
+      String::from("-an"),
+      String::from("-filter:v"),
+      filter,
+      String::from("-frames:v"),
+      MAX_SAMPLES.to_string(),
+      String::from("-f"),
+      String::from("null"),
+      String::from("-"),
+    ]);
+    let result = run_ffmpeg(&args, ProgressParser::new(None, None), cancel, |_| ());
+    let output = result.and_then(|_| {
+      fs::read_to_string(metadata_path).map_err(|error| format!("There was an error while analysing the video: {}", error))
+    });
+    let _ = fs::remove_file(metadata_path);
+
+    Ok(get_stable_crop(&parse_content_rectangles(&output?), (width, height)))
+  }
+}
@@ -0,0 +1,49 @@
+pub mod cancel {
+  use std::sync::{ atomic::{ AtomicBool, Ordering }, Arc };
+
+  /// Shared flag for cancelling a running job.
+  /// Clones share the same flag, so the job can be cancelled from another thread.
+  /// A job that ran out of time is cancelled as well, but remembers why.
+  ///
+  /// ## Usage:
+  /// ```
+  /// use drag_and_crop::utils::cancel::cancel::CancelToken;
+  ///
+  /// let token = CancelToken::new();
+  /// let job_token = token.clone();
+  /// assert!(!job_token.is_cancelled());
+  ///
+  /// token.cancel();
+  /// assert!(job_token.is_cancelled());
+  /// assert!(!job_token.is_timed_out());
+  /// ```
+  #[derive(Debug, Clone, Default)]
+  pub struct CancelToken {
+    cancelled: Arc<AtomicBool>,
+    timed_out: Arc<AtomicBool>,
+  }
+
+  impl CancelToken {
+    pub fn new() -> Self {
+      CancelToken::default()
+    }
+
+    pub fn cancel(&self) {
+      self.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+      self.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Cancels the job because it exceeded its time limit.
+    pub fn time_out(&self) {
+      self.timed_out.store(true, Ordering::SeqCst);
+      self.cancel();
+    }
+
+    pub fn is_timed_out(&self) -> bool {
+      self.timed_out.load(Ordering::SeqCst)
+    }
+  }
+}
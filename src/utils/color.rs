@@ -0,0 +1,131 @@
+pub mod color {
+  use image::{ ColorType, DynamicImage, ImageFormat };
+
+  /// Returns the color types the encoder of the format can write without
+  /// throwing away any of the channels.
+  pub fn get_supported_color_types(format: ImageFormat) -> &'static [ColorType] {
+    match format {
+      ImageFormat::Png =>
+        &[
+          ColorType::L8,
+          ColorType::La8,
+          ColorType::Rgb8,
+          ColorType::Rgba8,
+          ColorType::L16,
+          ColorType::La16,
+          ColorType::Rgb16,
+          ColorType::Rgba16,
+        ],
+      ImageFormat::Tiff =>
+        &[
+          ColorType::L8,
+          ColorType::Rgb8,
+          ColorType::Rgba8,
+          ColorType::L16,
+          ColorType::Rgb16,
+          ColorType::Rgba16,
+        ],
+      ImageFormat::Pnm => &[ColorType::L8, ColorType::Rgb8, ColorType::L16, ColorType::Rgb16],
+      ImageFormat::OpenExr => &[ColorType::Rgb32F, ColorType::Rgba32F],
+      ImageFormat::Jpeg => &[ColorType::L8, ColorType::Rgb8],
+      ImageFormat::Bmp | ImageFormat::Tga | ImageFormat::Ico =>
+        &[ColorType::L8, ColorType::La8, ColorType::Rgb8, ColorType::Rgba8],
+      _ => &[ColorType::Rgb8, ColorType::Rgba8],
+    }
+  }
+
+  /// Returns the color type that the image should be saved with.
+  /// If the format doesn't support the image's color type,
+  /// the closest supported one is picked - color and alpha channels are kept
+  /// whenever possible and the bit depth is reduced as little as possible.
+  ///
+  /// ## Usage:
+  /// ```
+  /// use image::{ ColorType, ImageFormat };
+  /// use drag_and_crop::utils::color::color::get_target_color_type;
+  ///
+  /// assert_eq!(get_target_color_type(ImageFormat::Png, ColorType::Rgb16), ColorType::Rgb16);
+  /// assert_eq!(get_target_color_type(ImageFormat::Tiff, ColorType::La16), ColorType::Rgba16);
+  /// assert_eq!(get_target_color_type(ImageFormat::Png, ColorType::Rgba32F), ColorType::Rgba16);
+  /// assert_eq!(get_target_color_type(ImageFormat::Jpeg, ColorType::L16), ColorType::L8);
+  /// ```
+  pub fn get_target_color_type(format: ImageFormat, color_type: ColorType) -> ColorType {
+    let supported = get_supported_color_types(format);
+    if supported.contains(&color_type) {
+      return color_type;
+    }
+
+    let source_depth = get_channel_depth(color_type);
+    *supported
+      .iter()
+      .min_by_key(|target| {
+        let loses_color = color_type.has_color() && !target.has_color();
+        let loses_alpha = color_type.has_alpha() && !target.has_alpha();
+        let target_depth = get_channel_depth(**target);
+        let depth_loss = source_depth.saturating_sub(target_depth);
+        let extra_bytes = target.bytes_per_pixel().saturating_sub(color_type.bytes_per_pixel());
+        (loses_color, loses_alpha, depth_loss, extra_bytes)
+      })
+      .unwrap_or(&ColorType::Rgba8)
+  }
+
+  /// Returns the number of bits per channel.
+  ///
+  /// ## Usage:
+  /// ```
+  /// use image::ColorType;
+  /// use drag_and_crop::utils::color::color::get_channel_depth;
+  ///
+  /// assert_eq!(get_channel_depth(ColorType::Rgba16), 16);
+  /// assert_eq!(get_channel_depth(ColorType::L8), 8);
+  /// ```
+  pub fn get_channel_depth(color_type: ColorType) -> u16 {
+    color_type.bits_per_pixel() / (color_type.channel_count() as u16)
+  }
+
+  /// Converts the image to the given color type.
+  pub fn convert_to_color_type(img: DynamicImage, color_type: ColorType) -> DynamicImage {
+    if img.color() == color_type {
+      return img;
+    }
+
+    match color_type {
+      ColorType::L8 => DynamicImage::ImageLuma8(img.to_luma8()),
+      ColorType::La8 => DynamicImage::ImageLumaA8(img.to_luma_alpha8()),
+      ColorType::Rgb8 => DynamicImage::ImageRgb8(img.to_rgb8()),
+      ColorType::L16 => DynamicImage::ImageLuma16(img.to_luma16()),
+      ColorType::La16 => DynamicImage::ImageLumaA16(img.to_luma_alpha16()),
+      ColorType::Rgb16 => DynamicImage::ImageRgb16(img.to_rgb16()),
+      ColorType::Rgba16 => DynamicImage::ImageRgba16(img.to_rgba16()),
+      ColorType::Rgb32F => DynamicImage::ImageRgb32F(img.to_rgb32f()),
+      ColorType::Rgba32F => DynamicImage::ImageRgba32F(img.to_rgba32f()),
+      _ => DynamicImage::ImageRgba8(img.to_rgba8()),
+    }
+  }
+
+  /// Prepares the image for the encoder of the given format.
+  /// Returns the converted image and a warning message if the color type had to be changed.
+  pub fn prepare_for_format(
+    img: DynamicImage,
+    format: ImageFormat
+  ) -> (DynamicImage, Option<String>) {
+    let color_type = img.color();
+    let target_color_type = get_target_color_type(format, color_type);
+    if target_color_type == color_type {
+      return (img, None);
+    }
+
+    let warning = format!(
+      "The image was converted from {:?} to {:?} because the {:?} format does not support {}.",
+      color_type,
+      target_color_type,
+      format,
+      if get_channel_depth(target_color_type) < get_channel_depth(color_type) {
+        format!("{}-bit samples", get_channel_depth(color_type))
+      } else {
+        String::from("its color type")
+      }
+    );
+    (convert_to_color_type(img, target_color_type), Some(warning))
+  }
+}
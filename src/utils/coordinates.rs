@@ -0,0 +1,105 @@
+pub mod coordinates {
+  use crate::{ CropDimensions, CropKeyframe, ImageSize, Point };
+  use crate::utils::probe::probe::VideoInfo;
+
+  /// Maps a crop from the displayed frame (square pixels) to the frame that reaches the ffmpeg filters.
+  /// Both frames are rotated the same way, so only anamorphic videos are scaled along the stretched axis.
+  /// The mapped crop always stays within the frame.
+  ///
+  /// ## Usage:
+  /// ```
+  /// use drag_and_crop::utils::coordinates::coordinates::map_to_frame;
+  /// use drag_and_crop::utils::probe::probe::VideoInfo;
+  /// use drag_and_crop::{ CropDimensions, ImageSize, Point };
+  ///
+  /// let dimensions = CropDimensions {
+  ///   top_left_point: Point { x: 100, y: 200 },
+  ///   size: ImageSize { width: 400, height: 300 },
+  /// };
+  /// // 1440x1080 stored, displayed as 1920x1080
+  /// let video = VideoInfo { width: 1440, height: 1080, sample_aspect_ratio: Some((4, 3)), ..Default::default() };
+  /// let mapped = map_to_frame(&dimensions, &video);
+  /// assert_eq!((mapped.top_left_point.x, mapped.top_left_point.y), (75, 200));
+  /// assert_eq!((mapped.size.width, mapped.size.height), (300, 300));
+  ///
+  /// // rotated by 90 degrees, the stretched (coded) width is displayed vertically
+  /// let video = VideoInfo { rotation: 90, ..video };
+  /// let mapped = map_to_frame(&dimensions, &video);
+  /// assert_eq!((mapped.top_left_point.x, mapped.top_left_point.y), (100, 150));
+  /// assert_eq!((mapped.size.width, mapped.size.height), (400, 225));
+  /// ```
+  pub fn map_to_frame(dimensions: &CropDimensions, video: &VideoInfo) -> CropDimensions {
+    let (frame_width, frame_height) = video.get_frame_size();
+    let (display_width, display_height) = video.get_display_size();
+    let (x, width) = map_axis(
+      dimensions.top_left_point.x,
+      dimensions.size.width,
+      frame_width,
+      display_width
+    );
+    let (y, height) = map_axis(
+      dimensions.top_left_point.y,
+      dimensions.size.height,
+      frame_height,
+      display_height
+    );
+    CropDimensions { top_left_point: Point { x, y }, size: ImageSize { width, height } }
+  }
+
+  /// Maps the crop size and the keyframes of a moving crop like `map_to_frame`.
+  pub fn map_keyframes_to_frame(
+    size: &ImageSize,
+    keyframes: &[CropKeyframe],
+    video: &VideoInfo
+  ) -> (ImageSize, Vec<CropKeyframe>) {
+    let mut frame_size = *size;
+    let frame_keyframes = keyframes
+      .iter()
+      .map(|keyframe| {
+        let dimensions = CropDimensions { top_left_point: keyframe.top_left_point, size: *size };
+        let mapped = map_to_frame(&dimensions, video);
+        frame_size = mapped.size;
+        CropKeyframe {
+          time: keyframe.time,
+          top_left_point: mapped.top_left_point,
+          interpolation: keyframe.interpolation,
+        }
+      })
+      .collect();
+    (frame_size, frame_keyframes)
+  }
+
+  /// Returns the filter that scales the cropped frame of an anamorphic video
+  /// to the requested (displayed) size with square pixels.
+  ///
+  /// ## Usage:
+  /// ```
+  /// use drag_and_crop::utils::coordinates::coordinates::get_square_pixel_filter;
+  /// use drag_and_crop::utils::probe::probe::VideoInfo;
+  /// use drag_and_crop::ImageSize;
+  ///
+  /// let size = ImageSize { width: 400, height: 300 };
+  /// let video = VideoInfo { width: 1440, height: 1080, sample_aspect_ratio: Some((4, 3)), ..Default::default() };
+  /// assert_eq!(get_square_pixel_filter(&size, &video), Some(String::from("scale=400:300,setsar=1")));
+  ///
+  /// let video = VideoInfo { width: 1920, height: 1080, sample_aspect_ratio: Some((1, 1)), ..Default::default() };
+  /// assert_eq!(get_square_pixel_filter(&size, &video), None);
+  /// ```
+  pub fn get_square_pixel_filter(size: &ImageSize, video: &VideoInfo) -> Option<String> {
+    if video.has_square_pixels() {
+      return None;
+    }
+    Some(format!("scale={}:{},setsar=1", size.width, size.height))
+  }
+
+  /// Maps the start and length of a crop along one axis, keeping the crop within the frame.
+  fn map_axis(start: u32, length: u32, frame_length: u32, display_length: u32) -> (u32, u32) {
+    if frame_length == display_length || display_length == 0 {
+      return (start, length);
+    }
+    let scale = (frame_length as f64) / (display_length as f64);
+    let length = (((length as f64) * scale).round() as u32).clamp(1, frame_length);
+    let start = (((start as f64) * scale).floor() as u32).min(frame_length - length);
+    (start, length)
+  }
+}
@@ -15,7 +15,7 @@ impl Fairing for CORS {
 
   async fn on_response<'r>(&self, _request: &'r Request<'_>, response: &mut Response<'r>) {
     response.set_header(Header::new("Access-Control-Allow-Origin", "*"));
-    response.set_header(Header::new("Access-Control-Allow-Methods", "POST, OPTIONS"));
+    response.set_header(Header::new("Access-Control-Allow-Methods", "GET, POST, OPTIONS"));
     response.set_header(Header::new("Access-Control-Allow-Headers", "*"));
     response.set_header(Header::new("Access-Control-Allow-Credentials", "true"));
   }
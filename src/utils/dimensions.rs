@@ -0,0 +1,106 @@
+pub mod dimensions {
+  use crate::{ CropDimensions, CropKeyframe, DimensionPolicy, ImageSize, Point };
+
+  /// Returns the crop adjusted to even dimensions, which encoders of chroma-subsampled video (e.g. yuv420p) require.
+  /// - `DimensionPolicy::RoundDown` drops the last odd row or column
+  /// - `DimensionPolicy::RoundUp` adds a row or column, moving the crop back if it reaches the edge of the frame
+  /// - `DimensionPolicy::Pad` keeps the crop, it's padded by `get_padding_filter`
+  ///
+  /// ## Usage:
+  /// ```
+  /// use drag_and_crop::utils::dimensions::dimensions::apply_dimension_policy;
+  /// use drag_and_crop::{ CropDimensions, DimensionPolicy, ImageSize, Point };
+  ///
+  /// let dimensions = CropDimensions {
+  ///   top_left_point: Point { x: 10, y: 681 },
+  ///   size: ImageSize { width: 501, height: 399 },
+  /// };
+  /// let rounded = apply_dimension_policy(&dimensions, DimensionPolicy::RoundDown, (1920, 1080)).unwrap();
+  /// assert_eq!((rounded.size.width, rounded.size.height), (500, 398));
+  ///
+  /// // the crop ends at the bottom edge, so it's moved up by one row
+  /// let rounded = apply_dimension_policy(&dimensions, DimensionPolicy::RoundUp, (1920, 1080)).unwrap();
+  /// assert_eq!((rounded.size.width, rounded.size.height), (502, 400));
+  /// assert_eq!((rounded.top_left_point.x, rounded.top_left_point.y), (10, 680));
+  /// ```
+  pub fn apply_dimension_policy(
+    dimensions: &CropDimensions,
+    policy: DimensionPolicy,
+    (width, height): (u32, u32)
+  ) -> Result<CropDimensions, String> {
+    let (x, crop_width) = apply_to_axis(dimensions.top_left_point.x, dimensions.size.width, width, policy)?;
+    let (y, crop_height) = apply_to_axis(dimensions.top_left_point.y, dimensions.size.height, height, policy)?;
+    Ok(CropDimensions { top_left_point: Point { x, y }, size: ImageSize { width: crop_width, height: crop_height } })
+  }
+
+  /// Applies the policy to the size and the position of every keyframe of a moving crop.
+  pub fn apply_dimension_policy_to_keyframes(
+    size: &ImageSize,
+    keyframes: &[CropKeyframe],
+    policy: DimensionPolicy,
+    frame_size: (u32, u32)
+  ) -> Result<(ImageSize, Vec<CropKeyframe>), String> {
+    let mut even_size = *size;
+    let mut even_keyframes = Vec::new();
+    for keyframe in keyframes {
+      let dimensions = CropDimensions { top_left_point: keyframe.top_left_point, size: *size };
+      let even_dimensions = apply_dimension_policy(&dimensions, policy, frame_size)?;
+      even_size = even_dimensions.size;
+      even_keyframes.push(CropKeyframe { top_left_point: even_dimensions.top_left_point, ..keyframe.clone() });
+    }
+    Ok((even_size, even_keyframes))
+  }
+
+  /// Returns the filter that pads the crop to even dimensions, if it's needed by the policy.
+  ///
+  /// ## Usage:
+  /// ```
+  /// use drag_and_crop::utils::dimensions::dimensions::{ get_output_size, get_padding_filter };
+  /// use drag_and_crop::{ DimensionPolicy, ImageSize };
+  ///
+  /// let size = ImageSize { width: 501, height: 400 };
+  /// assert_eq!(get_padding_filter(&size, DimensionPolicy::Pad), Some(String::from("pad=502:400:0:0")));
+  /// assert_eq!(get_padding_filter(&size, DimensionPolicy::RoundDown), None);
+  /// assert_eq!(get_output_size(&size, DimensionPolicy::Pad).width, 502);
+  /// ```
+  pub fn get_padding_filter(size: &ImageSize, policy: DimensionPolicy) -> Option<String> {
+    if policy != DimensionPolicy::Pad || (size.width.is_multiple_of(2) && size.height.is_multiple_of(2)) {
+      return None;
+    }
+    let output_size = get_output_size(size, policy);
+    Some(format!("pad={}:{}:0:0", output_size.width, output_size.height))
+  }
+
+  /// Returns the size of the output, after the padding.
+  pub fn get_output_size(size: &ImageSize, policy: DimensionPolicy) -> ImageSize {
+    match policy {
+      DimensionPolicy::Pad => ImageSize { width: size.width + (size.width % 2), height: size.height + (size.height % 2) },
+      _ => *size,
+    }
+  }
+
+  fn apply_to_axis(start: u32, length: u32, frame_length: u32, policy: DimensionPolicy) -> Result<(u32, u32), String> {
+    if length.is_multiple_of(2) || policy == DimensionPolicy::Pad {
+      return Ok((start, length));
+    }
+    match policy {
+      DimensionPolicy::RoundDown => {
+        if length < 2 {
+          return Err(String::from("The cropped video must be at least 2px wide and high."));
+        }
+        Ok((start, length - 1))
+      }
+      DimensionPolicy::RoundUp => {
+        if length + 1 > frame_length {
+          return Err(
+            String::from("The crop can't be rounded up to even dimensions, because the video frame is too small.")
+          );
+        }
+        // the crop is moved back if it would reach past the edge of the frame
+        let start = start.min(frame_length - (length + 1));
+        Ok((start, length + 1))
+      }
+      DimensionPolicy::Pad => Ok((start, length)),
+    }
+  }
+}
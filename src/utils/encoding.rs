@@ -0,0 +1,200 @@
+pub mod encoding {
+  use std::path::Path;
+
+  use crate::{ EncodingProfile, VideoCodec };
+  use crate::utils::ffmpeg::ffmpeg::{ get_ffmpeg, get_pixel_formats };
+  use crate::utils::format::format::{ find_format, MediaType };
+
+  const X26X_PRESETS: [&str; 10] = [
+    "ultrafast",
+    "superfast",
+    "veryfast",
+    "faster",
+    "fast",
+    "medium",
+    "slow",
+    "slower",
+    "veryslow",
+    "placebo",
+  ];
+
+  /// Containers that can store encoded videos.
+  const ENCODING_CONTAINERS: [&str; 4] = ["mp4", "mkv", "mov", "webm"];
+
+  /// Container of encoded videos whose input container can't store them.
+  const DEFAULT_CONTAINER: &str = "mp4";
+
+  /// Returns the encoders of the codec, in the order of preference.
+  pub fn get_encoders(codec: VideoCodec) -> &'static [&'static str] {
+    match codec {
+      VideoCodec::H264 => &["libx264"],
+      VideoCodec::H265 => &["libx265"],
+      VideoCodec::Vp9 => &["libvpx-vp9"],
+      VideoCodec::Av1 => &["libsvtav1", "libaom-av1"],
+    }
+  }
+
+  /// Returns `true` if the container can store the codec.
+  ///
+  /// ## Usage:
+  /// ```
+  /// use drag_and_crop::utils::encoding::encoding::is_supported_container;
+  /// use drag_and_crop::VideoCodec;
+  ///
+  /// assert!(is_supported_container(VideoCodec::H265, "mkv"));
+  /// assert!(is_supported_container(VideoCodec::Av1, "webm"));
+  /// assert!(!is_supported_container(VideoCodec::H264, "webm"));
+  /// ```
+  pub fn is_supported_container(codec: VideoCodec, container: &str) -> bool {
+    match container {
+      "mp4" | "mkv" => true,
+      "mov" => codec != VideoCodec::Vp9,
+      "webm" => matches!(codec, VideoCodec::Vp9 | VideoCodec::Av1),
+      _ => false,
+    }
+  }
+
+  /// Returns the extension of the output container.
+  /// Encoded videos are stored in mp4 if the input container can't store the encoded codecs (e.g. avi).
+  ///
+  /// ## Usage:
+  /// ```
+  /// use drag_and_crop::utils::encoding::encoding::get_container;
+  /// use drag_and_crop::EncodingProfile;
+  ///
+  /// assert_eq!(get_container(None, "./tmp/video.MP4"), "mp4");
+  /// let profile = EncodingProfile { container: Some(String::from("webm")), ..Default::default() };
+  /// assert_eq!(get_container(Some(&profile), "./tmp/video.mp4"), "webm");
+  /// assert_eq!(get_container(None, "./tmp/video.avi"), "avi");
+  /// assert_eq!(get_container(Some(&EncodingProfile::default()), "./tmp/video.avi"), "mp4");
+  /// ```
+  pub fn get_container(profile: Option<&EncodingProfile>, input_file_path: &str) -> String {
+    let input_container = Path::new(input_file_path)
+      .extension()
+      .map(|extension| extension.to_string_lossy().to_lowercase())
+      .unwrap_or_default();
+    match profile {
+      Some(profile) =>
+        match &profile.container {
+          Some(container) => container.to_lowercase(),
+          None if !ENCODING_CONTAINERS.contains(&input_container.as_str()) => String::from(DEFAULT_CONTAINER),
+          None => input_container,
+        }
+      None => input_container,
+    }
+  }
+
+  /// Validates the encoding profile against the detected ffmpeg
+  /// and returns the ffmpeg output arguments.
+  /// The container defaults to the extension of the input file, or mp4 if it can't store encoded videos.
+  pub fn get_encoding_args(
+    profile: &EncodingProfile,
+    input_file_path: &str
+  ) -> Result<Vec<String>, String> {
+    let container = get_container(Some(profile), input_file_path);
+    match find_format(&container) {
+      Some(format) if format.media_type == MediaType::Video => (),
+      _ => {
+        return Err(format!("The output container \"{}\" is not supported.", container));
+      }
+    }
+    let codec = profile.codec.unwrap_or(if container == "webm" {
+      VideoCodec::Vp9
+    } else {
+      VideoCodec::H264
+    });
+    if !is_supported_container(codec, &container) {
+      return Err(
+        format!("The {} codec can't be stored in the {} container.", get_codec_name(codec), container)
+      );
+    }
+
+    // 1) codec
+    let ffmpeg = get_ffmpeg()?;
+    let encoder = get_encoders(codec)
+      .iter()
+      .find(|encoder| ffmpeg.video_encoders.iter().any(|name| name == *encoder));
+    let encoder = match encoder {
+      Some(encoder) => *encoder,
+      None => {
+        return Err(
+          format!("The {} codec is not supported by the installed ffmpeg.", get_codec_name(codec))
+        );
+      }
+    };
+    let mut args = vec![String::from("-c:v"), String::from(encoder)];
+
+    // 2) quality
+    if profile.crf.is_some() && profile.bitrate.is_some() {
+      return Err(String::from("The CRF and bitrate can't be set at the same time."));
+    }
+    if let Some(crf) = profile.crf {
+      let max_crf = if codec == VideoCodec::H264 || codec == VideoCodec::H265 { 51 } else { 63 };
+      if crf > max_crf {
+        return Err(
+          format!("The CRF of the {} codec must be between 0 and {}.", get_codec_name(codec), max_crf)
+        );
+      }
+      args.extend([String::from("-crf"), crf.to_string()]);
+      // without a bitrate of 0, libvpx and libaom only use the CRF as a quality limit
+      if encoder == "libvpx-vp9" || encoder == "libaom-av1" {
+        args.extend([String::from("-b:v"), String::from("0")]);
+      }
+    }
+    if let Some(bitrate) = profile.bitrate {
+      if bitrate == 0 {
+        return Err(String::from("The bitrate must be larger than 0."));
+      }
+      args.extend([String::from("-b:v"), format!("{}k", bitrate)]);
+    }
+
+    // 3) preset
+    if let Some(preset) = &profile.preset {
+      args.extend(get_preset_args(encoder, preset)?);
+    }
+
+    // 4) pixel format
+    if let Some(pixel_format) = &profile.pixel_format {
+      if !get_pixel_formats(encoder)?.contains(pixel_format) {
+        return Err(
+          format!("The pixel format \"{}\" is not supported by {}.", pixel_format, encoder)
+        );
+      }
+      args.extend([String::from("-pix_fmt"), pixel_format.clone()]);
+    }
+
+    // Apple players only recognize h265 with the "hvc1" tag
+    if codec == VideoCodec::H265 && (container == "mp4" || container == "mov") {
+      args.extend([String::from("-tag:v"), String::from("hvc1")]);
+    }
+
+    Ok(args)
+  }
+
+  fn get_preset_args(encoder: &str, preset: &str) -> Result<Vec<String>, String> {
+    let (option, is_valid) = match encoder {
+      "libx264" | "libx265" => ("-preset", X26X_PRESETS.contains(&preset)),
+      "libsvtav1" => ("-preset", preset.parse::<u32>().is_ok_and(|preset| preset <= 13)),
+      _ => ("-cpu-used", preset.parse::<u32>().is_ok_and(|preset| preset <= 8)),
+    };
+    if !is_valid {
+      let expected = match encoder {
+        "libx264" | "libx265" => format!("one of {}", X26X_PRESETS.join(", ")),
+        "libsvtav1" => String::from("a number between 0 and 13"),
+        _ => String::from("a number between 0 and 8"),
+      };
+      return Err(format!("The preset of {} must be {}.", encoder, expected));
+    }
+
+    Ok(vec![String::from(option), String::from(preset)])
+  }
+
+  fn get_codec_name(codec: VideoCodec) -> &'static str {
+    match codec {
+      VideoCodec::H264 => "h264",
+      VideoCodec::H265 => "h265",
+      VideoCodec::Vp9 => "vp9",
+      VideoCodec::Av1 => "av1",
+    }
+  }
+}
@@ -0,0 +1,467 @@
+pub mod ffmpeg {
+  use std::{
+    env,
+    io::{ BufRead, BufReader, Read },
+    path::{ Path, PathBuf },
+    process::{ Command, ExitStatus, Stdio },
+    sync::{ atomic::{ AtomicBool, Ordering }, Arc, Mutex, OnceLock },
+    thread,
+    time::{ Duration, Instant },
+  };
+  use serde::{ Serialize, Deserialize };
+
+  use crate::utils::cancel::cancel::CancelToken;
+  use crate::utils::limits::limits::ProcessLimits;
+  use crate::utils::progress::progress::{ ProgressParser, VideoProgress };
+
+  pub const FFMPEG_PATH_VARIABLE: &str = "DRAG_N_CROP_FFMPEG_PATH";
+  pub const FFPROBE_PATH_VARIABLE: &str = "DRAG_N_CROP_FFPROBE_PATH";
+
+  /// How often a running ffmpeg process checks if it was cancelled.
+  const CANCEL_CHECK_INTERVAL: Duration = Duration::from_millis(100);
+
+  static FFMPEG: OnceLock<Result<FfmpegInfo, String>> = OnceLock::new();
+
+  /// Explicitly configured binary paths. They take precedence over the environment variables.
+  #[derive(Serialize, Deserialize, Debug, Default, Clone)]
+  pub struct FfmpegConfig {
+    pub ffmpeg_path: Option<String>,
+    pub ffprobe_path: Option<String>,
+    /// Starts the server without video processing if ffmpeg can't be found. Defaults to `false`.
+    pub ffmpeg_optional: Option<bool>,
+  }
+
+  /// The ffmpeg installation that was detected and its capabilities.
+  #[derive(Serialize, Deserialize, Debug, Clone)]
+  pub struct FfmpegInfo {
+    pub ffmpeg_path: String,
+    pub ffprobe_path: String,
+    pub version: String,
+    pub ffprobe_version: String,
+    pub video_encoders: Vec<String>,
+    pub audio_encoders: Vec<String>,
+    pub filters: Vec<String>,
+  }
+
+  /// Detects ffmpeg with the given configuration and stores the result for the rest of the session.
+  /// Should be called once at startup - calling it again returns the first result.
+  pub fn init_ffmpeg(config: &FfmpegConfig) -> Result<&'static FfmpegInfo, String> {
+    FFMPEG.get_or_init(|| detect_ffmpeg(config)).as_ref().map_err(String::clone)
+  }
+
+  /// Returns the detected ffmpeg installation.
+  /// If `init_ffmpeg` wasn't called, ffmpeg is detected with the default configuration.
+  pub fn get_ffmpeg() -> Result<&'static FfmpegInfo, String> {
+    init_ffmpeg(&FfmpegConfig::default())
+  }
+
+  /// Finds ffmpeg and ffprobe, checks their versions and reads the ffmpeg capabilities.
+  pub fn detect_ffmpeg(config: &FfmpegConfig) -> Result<FfmpegInfo, String> {
+    let ffmpeg_path = find_binary("ffmpeg", config.ffmpeg_path.as_deref(), FFMPEG_PATH_VARIABLE)?;
+    let ffprobe_path = find_binary(
+      "ffprobe",
+      config.ffprobe_path.as_deref(),
+      FFPROBE_PATH_VARIABLE
+    )?;
+    let version = get_version(&ffmpeg_path)?;
+    let ffprobe_version = get_version(&ffprobe_path)?;
+
+    let encoders_output = run_binary(&ffmpeg_path, &["-hide_banner", "-encoders"])?;
+    let filters_output = run_binary(&ffmpeg_path, &["-hide_banner", "-filters"])?;
+
+    Ok(FfmpegInfo {
+      ffmpeg_path: ffmpeg_path.to_string_lossy().to_string(),
+      ffprobe_path: ffprobe_path.to_string_lossy().to_string(),
+      version,
+      ffprobe_version,
+      video_encoders: parse_encoders(&encoders_output, 'V'),
+      audio_encoders: parse_encoders(&encoders_output, 'A'),
+      filters: parse_filters(&filters_output),
+    })
+  }
+
+  /// Looks for the binary in the following order:
+  /// 1) configured path
+  /// 2) environment variable
+  /// 3) directories from `PATH`
+  /// 4) root of the project
+  ///
+  /// A candidate is only accepted if it responds to `-version`.
+  pub fn find_binary(
+    name: &str,
+    configured_path: Option<&str>,
+    variable: &str
+  ) -> Result<PathBuf, String> {
+    if let Some(path) = configured_path {
+      return check_binary(name, PathBuf::from(path));
+    }
+    if let Ok(path) = env::var(variable) {
+      return check_binary(name, PathBuf::from(path));
+    }
+
+    let file_names = [name.to_string(), format!("{}.exe", name)];
+    let mut directories: Vec<PathBuf> = env::var_os("PATH")
+      .map(|paths| env::split_paths(&paths).collect())
+      .unwrap_or_default();
+    directories.push(PathBuf::from("."));
+
+    for directory in directories {
+      for file_name in &file_names {
+        let candidate = directory.join(file_name);
+        if candidate.is_file() && get_version(&candidate).is_ok() {
+          return Ok(candidate);
+        }
+      }
+    }
+
+    Err(
+      format!(
+        "Could not find {}. Install it, place it in the root of the project or set the {} environment variable.",
+        name,
+        variable
+      )
+    )
+  }
+
+  /// Output of a process that ran within the `ProcessLimits`.
+  #[derive(Debug)]
+  pub struct ProcessOutput {
+    pub status: ExitStatus,
+    /// Standard output of the process.
+    pub output: String,
+    /// End of the error output, up to `max_error_output` bytes.
+    pub errors: String,
+  }
+
+  /// Runs ffmpeg with the given arguments and calls `on_progress` whenever it reports progress.
+  /// The process runs within the `ProcessLimits` from the environment, and it's killed
+  /// as soon as the job is cancelled or the timeout is exceeded (which cancels the whole job).
+  /// Returns the error output if ffmpeg fails.
+  pub fn run_ffmpeg(
+    args: &[String],
+    mut parser: ProgressParser,
+    cancel: &CancelToken,
+    mut on_progress: impl FnMut(&VideoProgress)
+  ) -> Result<(), String> {
+    let limits = ProcessLimits::from_env();
+    if cancel.is_cancelled() {
+      return Err(get_cancel_error(cancel, &limits));
+    }
+    let ffmpeg = get_ffmpeg()?;
+    // nice replaces itself with ffmpeg (exec), so the child is still ffmpeg and killing it stops the processing
+    let mut command = if limits.nice != 0 && cfg!(unix) {
+      let mut command = Command::new("nice");
+      command.args(["-n", &limits.nice.to_string()]).arg(&ffmpeg.ffmpeg_path);
+      command
+    } else {
+      Command::new(&ffmpeg.ffmpeg_path)
+    };
+    command
+      .args(["-hide_banner", "-nostdin", "-loglevel", "error", "-nostats", "-progress", "pipe:1"])
+      .args(get_thread_args(args, limits.threads));
+
+    // the progress lines are parsed and dropped, so long jobs don't accumulate their output
+    let (status, errors) = run_process(command, &limits, cancel, |line| {
+      if let Some(progress) = parser.parse_line(line) {
+        on_progress(&progress);
+      }
+    })?;
+    if !status.success() {
+      return Err(format!("{}\n{}", status, errors.trim()));
+    }
+
+    Ok(())
+  }
+
+  /// Runs ffprobe with the given arguments within the same limits as ffmpeg and returns its output.
+  /// The exit status is left to the caller, which knows what a failure means.
+  pub fn run_ffprobe(args: &[&str], cancel: &CancelToken) -> Result<ProcessOutput, String> {
+    let limits = ProcessLimits::from_env();
+    if cancel.is_cancelled() {
+      return Err(get_cancel_error(cancel, &limits));
+    }
+    let ffmpeg = get_ffmpeg()?;
+    let mut command = Command::new(&ffmpeg.ffprobe_path);
+    command.args(args);
+    let mut output = String::new();
+    let (status, errors) = run_process(command, &limits, cancel, |line| {
+      output.push_str(line);
+      output.push('\n');
+    })?;
+    Ok(ProcessOutput { status, output, errors })
+  }
+
+  /// Runs the command, calls `on_line` for every line of its output and returns the exit status and the end of stderr.
+  /// The output isn't kept, callers that need it collect the lines.
+  /// stderr is read on a separate thread so that neither of the pipes can fill up and block the process,
+  /// and only its end is kept. The process is killed as soon as the job is cancelled or the timeout is exceeded.
+  fn run_process(
+    mut command: Command,
+    limits: &ProcessLimits,
+    cancel: &CancelToken,
+    mut on_line: impl FnMut(&str)
+  ) -> Result<(ExitStatus, String), String> {
+    let program = command.get_program().to_string_lossy().to_string();
+    let child = command.stdin(Stdio::null()).stdout(Stdio::piped()).stderr(Stdio::piped()).spawn();
+    let mut child = match child {
+      Ok(child) => child,
+      Err(error) => {
+        return Err(format!("Failed to run {}: {}", program, error));
+      }
+    };
+
+    let mut stderr = child.stderr.take().unwrap();
+    let max_error_output = limits.max_error_output;
+    let stderr_reader = thread::spawn(move || read_error_output(&mut stderr, max_error_output));
+    let stdout = child.stdout.take().unwrap();
+
+    // the process can stop writing output (e.g. while ffmpeg is probing), so the cancellation is watched separately
+    let child = Arc::new(Mutex::new(child));
+    let finished = Arc::new(AtomicBool::new(false));
+    let deadline = if limits.timeout > 0 {
+      Some(Instant::now() + Duration::from_secs(limits.timeout))
+    } else {
+      None
+    };
+    let watcher = {
+      let child = Arc::clone(&child);
+      let finished = Arc::clone(&finished);
+      let cancel = cancel.clone();
+      thread::spawn(move || {
+        while !finished.load(Ordering::SeqCst) {
+          if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            cancel.time_out();
+          }
+          if cancel.is_cancelled() {
+            let _ = child.lock().unwrap().kill();
+            return;
+          }
+          thread::sleep(CANCEL_CHECK_INTERVAL);
+        }
+      })
+    };
+
+    let mut reader = BufReader::new(stdout);
+    let mut line = Vec::new();
+    while reader.read_until(b'\n', &mut line).unwrap_or(0) > 0 {
+      on_line(String::from_utf8_lossy(&line).trim_end_matches(['\r', '\n']));
+      line.clear();
+    }
+    finished.store(true, Ordering::SeqCst);
+    let _ = watcher.join();
+
+    let status = match child.lock().unwrap().wait() {
+      Ok(status) => status,
+      Err(error) => {
+        return Err(format!("Failed to run {}: {}", program, error));
+      }
+    };
+    let errors = stderr_reader.join().unwrap_or_default();
+    if cancel.is_cancelled() {
+      return Err(get_cancel_error(cancel, limits));
+    }
+
+    Ok((status, errors))
+  }
+
+  fn get_cancel_error(cancel: &CancelToken, limits: &ProcessLimits) -> String {
+    if cancel.is_timed_out() {
+      format!("The video processing took longer than the limit of {} seconds and was stopped.", limits.timeout)
+    } else {
+      String::from("The video processing was cancelled.")
+    }
+  }
+
+  /// Limits the threads of the decoders (before each input), the filters and the encoders (before the output).
+  ///
+  /// ## Usage:
+  /// ```
+  /// use drag_and_crop::utils::ffmpeg::ffmpeg::get_thread_args;
+  ///
+  /// let args = ["-i", "in.mp4", "-an", "out.mp4"].map(String::from);
+  /// assert_eq!(get_thread_args(&args, 0), args);
+  /// assert_eq!(
+  ///   get_thread_args(&args, 2),
+  ///   vec![
+  ///     "-filter_threads", "2", "-filter_complex_threads", "2",
+  ///     "-threads", "2", "-i", "in.mp4", "-an", "-threads", "2", "out.mp4"
+  ///   ]
+  /// );
+  /// ```
+  pub fn get_thread_args(args: &[String], threads: u32) -> Vec<String> {
+    if threads == 0 {
+      return args.to_vec();
+    }
+    let threads = threads.to_string();
+    let mut thread_args = ["-filter_threads", &threads, "-filter_complex_threads", &threads]
+      .map(String::from)
+      .to_vec();
+    for (index, arg) in args.iter().enumerate() {
+      if arg == "-i" || index == args.len() - 1 {
+        thread_args.extend([String::from("-threads"), threads.clone()]);
+      }
+      thread_args.push(arg.clone());
+    }
+    thread_args
+  }
+
+  /// Reads the error output until the end, but only keeps its last `max_length` bytes.
+  ///
+  /// ## Usage:
+  /// ```
+  /// use drag_and_crop::utils::ffmpeg::ffmpeg::read_error_output;
+  ///
+  /// assert_eq!(read_error_output(&mut "error 1\nerror 2".as_bytes(), 100), "error 1\nerror 2");
+  /// assert_eq!(read_error_output(&mut "error 1\nerror 2".as_bytes(), 7), "...error 2");
+  /// ```
+  pub fn read_error_output(reader: &mut impl Read, max_length: usize) -> String {
+    let mut output = Vec::new();
+    let mut buffer = [0; 8192];
+    let mut is_truncated = false;
+    while let Ok(length) = reader.read(&mut buffer) {
+      if length == 0 {
+        break;
+      }
+      output.extend_from_slice(&buffer[..length]);
+      if output.len() > max_length {
+        output.drain(..output.len() - max_length);
+        is_truncated = true;
+      }
+    }
+
+    let output = String::from_utf8_lossy(&output).to_string();
+    if is_truncated { format!("...{}", output) } else { output }
+  }
+
+  /// Returns the pixel formats that the encoder supports.
+  pub fn get_pixel_formats(encoder: &str) -> Result<Vec<String>, String> {
+    let ffmpeg = get_ffmpeg()?;
+    let output = run_binary(
+      Path::new(&ffmpeg.ffmpeg_path),
+      &["-hide_banner", "-h", &format!("encoder={}", encoder)]
+    )?;
+    Ok(parse_pixel_formats(&output))
+  }
+
+  fn check_binary(name: &str, path: PathBuf) -> Result<PathBuf, String> {
+    match get_version(&path) {
+      Ok(_) => Ok(path),
+      Err(error) => Err(format!("The configured {} binary is not usable: {}", name, error)),
+    }
+  }
+
+  /// Runs the binary within the `ProcessLimits`, so that a hanging candidate can't block the detection.
+  fn run_binary(path: &Path, args: &[&str]) -> Result<String, String> {
+    let mut command = Command::new(path);
+    command.args(args);
+    let mut output = String::new();
+    let (status, _) = run_process(command, &ProcessLimits::from_env(), &CancelToken::new(), |line| {
+      output.push_str(line);
+      output.push('\n');
+    })?;
+    if !status.success() {
+      return Err(format!("{} exited with {}", path.display(), status));
+    }
+
+    Ok(output)
+  }
+
+  fn get_version(path: &Path) -> Result<String, String> {
+    let output = run_binary(path, &["-version"])?;
+    match parse_version(&output) {
+      Some(version) => Ok(version),
+      None => Err(format!("{} did not report its version.", path.display())),
+    }
+  }
+
+  /// Parses the version from the output of `ffmpeg -version`.
+  ///
+  /// ## Usage:
+  /// ```
+  /// use drag_and_crop::utils::ffmpeg::ffmpeg::parse_version;
+  ///
+  /// let output = "ffmpeg version 6.0-static https://johnvansickle.com/ffmpeg/  Copyright (c) 2000-2023";
+  /// assert_eq!(parse_version(output), Some(String::from("6.0-static")));
+  /// assert_eq!(parse_version("command not found"), None);
+  /// ```
+  pub fn parse_version(output: &str) -> Option<String> {
+    let first_line = output.lines().next()?;
+    let mut words = first_line.split_whitespace();
+    words.find(|word| *word == "version")?;
+    words.next().map(String::from)
+  }
+
+  /// Parses the encoder names of the given type ('V' - video, 'A' - audio, 'S' - subtitle)
+  /// from the output of `ffmpeg -encoders`.
+  ///
+  /// ## Usage:
+  /// ```
+  /// use drag_and_crop::utils::ffmpeg::ffmpeg::parse_encoders;
+  ///
+  /// let output = "Encoders:
+  ///  V..... = Video
+  ///  ------
+  ///  V....D libx264              libx264 H.264 / AVC / MPEG-4 AVC (codec h264)
+  ///  A....D aac                  AAC (Advanced Audio Coding)";
+  /// assert_eq!(parse_encoders(output, 'V'), vec!["libx264"]);
+  /// assert_eq!(parse_encoders(output, 'A'), vec!["aac"]);
+  /// ```
+  pub fn parse_encoders(output: &str, encoder_type: char) -> Vec<String> {
+    output
+      .lines()
+      .skip_while(|line| !line.trim_start().starts_with("---"))
+      .skip(1)
+      .filter_map(|line| {
+        let mut words = line.split_whitespace();
+        let flags = words.next()?;
+        let name = words.next()?;
+        if flags.starts_with(encoder_type) { Some(String::from(name)) } else { None }
+      })
+      .collect()
+  }
+
+  /// Parses the supported pixel formats from the output of `ffmpeg -h encoder=<name>`.
+  ///
+  /// ## Usage:
+  /// ```
+  /// use drag_and_crop::utils::ffmpeg::ffmpeg::parse_pixel_formats;
+  ///
+  /// let output = "Encoder libx264 [libx264 H.264 / AVC / MPEG-4 AVC]:
+  ///     General capabilities: dr1 delay threads
+  ///     Supported pixel formats: yuv420p yuvj420p yuv420p10le";
+  /// assert_eq!(parse_pixel_formats(output), vec!["yuv420p", "yuvj420p", "yuv420p10le"]);
+  /// ```
+  pub fn parse_pixel_formats(output: &str) -> Vec<String> {
+    output
+      .lines()
+      .find_map(|line| line.trim().strip_prefix("Supported pixel formats:"))
+      .map(|formats| formats.split_whitespace().map(String::from).collect())
+      .unwrap_or_default()
+  }
+
+  /// Parses the filter names from the output of `ffmpeg -filters`.
+  ///
+  /// ## Usage:
+  /// ```
+  /// use drag_and_crop::utils::ffmpeg::ffmpeg::parse_filters;
+  ///
+  /// let output = "Filters:
+  ///   T.. = Timeline support
+  ///  TSC crop              V->V       Crop the input video.
+  ///  ... palettegen        V->V       Find the optimal palette for a given stream.";
+  /// assert_eq!(parse_filters(output), vec!["crop", "palettegen"]);
+  /// ```
+  pub fn parse_filters(output: &str) -> Vec<String> {
+    output
+      .lines()
+      .filter_map(|line| {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        if words.len() >= 3 && words[2].contains("->") {
+          Some(String::from(words[1]))
+        } else {
+          None
+        }
+      })
+      .collect()
+  }
+}
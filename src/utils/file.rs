@@ -1,5 +1,6 @@
 pub mod file {
   use std::path::{ Path, PathBuf };
+  use crate::utils::format::format::{ find_format_by_path, MediaType };
 
   /// Changes file name (without extension) and returns the full path.
   ///
@@ -118,20 +119,11 @@ pub mod file {
   /// assert_eq!(is_image_2, false);
   /// ```
   ///
-  /// ## Supported video formats:
-  /// "png" | "jpg" | "jpeg" | "gif" | "bmp" | "webp" | "svg"
+  /// ## Supported image formats:
+  /// "png" | "jpg" | "jpeg" | "gif" | "bmp" | "webp" | "svg" | "tiff" | "tif" | "ico" | "tga" |
+  /// "qoi" (see `MEDIA_FORMATS`)
   pub fn is_image_file(file_path: &str) -> bool {
-    let path = Path::new(file_path);
-    match path.extension().and_then(std::ffi::OsStr::to_str) {
-      Some(ext) => {
-        let ext_lower = ext.to_lowercase();
-        match ext_lower.as_str() {
-          "png" | "jpg" | "jpeg" | "gif" | "bmp" | "webp" | "svg" => true,
-          _ => false,
-        }
-      }
-      None => false,
-    }
+    matches!(find_format_by_path(file_path), Some(format) if format.media_type == MediaType::Image)
   }
 
   /// Returns `true` if the file is an video.
@@ -150,18 +142,8 @@ pub mod file {
   /// ```
   ///
   /// ## Supported video formats:
-  /// "mp4" | "mkv" | "flv" | "avi" | "mov" | "wmv" | "webm"
+  /// "mp4" | "mkv" | "flv" | "avi" | "mov" | "wmv" | "webm" (see `MEDIA_FORMATS`)
   pub fn is_video_file(file_path: &str) -> bool {
-    let path = Path::new(file_path);
-    match path.extension().and_then(std::ffi::OsStr::to_str) {
-      Some(ext) => {
-        let ext_lower = ext.to_lowercase();
-        match ext_lower.as_str() {
-          "mp4" | "mkv" | "flv" | "avi" | "mov" | "wmv" | "webm" => true,
-          _ => false,
-        }
-      }
-      None => false,
-    }
+    matches!(find_format_by_path(file_path), Some(format) if format.media_type == MediaType::Video)
   }
 }
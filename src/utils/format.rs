@@ -0,0 +1,119 @@
+pub mod format {
+  use std::path::Path;
+  use image::ImageFormat;
+
+  #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+  pub enum MediaType {
+    Image,
+    Video,
+  }
+
+  /// Describes a supported file format.
+  #[derive(Debug)]
+  pub struct MediaFormat {
+    pub extensions: &'static [&'static str],
+    pub mime_type: &'static str,
+    pub media_type: MediaType,
+    /// Matching format of the `image` crate (images only).
+    pub image_format: Option<ImageFormat>,
+    /// `true` if the file can be opened for cropping.
+    pub can_decode: bool,
+    /// `true` if cropped files can be saved in this format.
+    pub can_encode: bool,
+  }
+
+  const fn image(
+    extensions: &'static [&'static str],
+    mime_type: &'static str,
+    image_format: Option<ImageFormat>,
+    can_decode: bool,
+    can_encode: bool
+  ) -> MediaFormat {
+    MediaFormat {
+      extensions,
+      mime_type,
+      media_type: MediaType::Image,
+      image_format,
+      can_decode,
+      can_encode,
+    }
+  }
+
+  const fn video(extensions: &'static [&'static str], mime_type: &'static str) -> MediaFormat {
+    MediaFormat {
+      extensions,
+      mime_type,
+      media_type: MediaType::Video,
+      image_format: None,
+      can_decode: true,
+      can_encode: true,
+    }
+  }
+
+  /// All supported formats.
+  /// The image codecs follow the enabled features:
+  /// - WebP encoding needs the "webp-encoder" feature (enabled by default)
+  /// - AVIF is not supported, decoding it needs the dav1d system library
+  /// - SVG is not a raster format and can only be uploaded
+  pub const MEDIA_FORMATS: &[MediaFormat] = &[
+    image(&["png"], "image/png", Some(ImageFormat::Png), true, true),
+    image(&["jpg", "jpeg"], "image/jpeg", Some(ImageFormat::Jpeg), true, true),
+    image(&["gif"], "image/gif", Some(ImageFormat::Gif), true, true),
+    image(&["bmp"], "image/bmp", Some(ImageFormat::Bmp), true, true),
+    image(&["webp"], "image/webp", Some(ImageFormat::WebP), true, cfg!(feature = "webp-encoder")),
+    image(&["svg"], "image/svg+xml", None, false, false),
+    image(&["tiff", "tif"], "image/tiff", Some(ImageFormat::Tiff), true, true),
+    image(&["ico"], "image/x-icon", Some(ImageFormat::Ico), true, true),
+    image(&["tga"], "image/x-tga", Some(ImageFormat::Tga), true, true),
+    image(&["qoi"], "image/x-qoi", Some(ImageFormat::Qoi), true, true),
+    video(&["mp4"], "video/mp4"),
+    video(&["mkv"], "video/x-matroska"),
+    video(&["flv"], "video/x-flv"),
+    video(&["avi"], "video/x-msvideo"),
+    video(&["mov"], "video/quicktime"),
+    video(&["wmv"], "video/x-ms-wmv"),
+    video(&["webm"], "video/webm"),
+  ];
+
+  /// Returns the format registered for the extension (case insensitive, without the dot).
+  ///
+  /// ## Usage:
+  /// ```
+  /// use drag_and_crop::utils::format::format::{ find_format, MediaType };
+  ///
+  /// let format = find_format("TIF").unwrap();
+  /// assert_eq!(format.mime_type, "image/tiff");
+  /// assert_eq!(format.media_type, MediaType::Image);
+  /// assert!(find_format("txt").is_none());
+  /// ```
+  pub fn find_format(extension: &str) -> Option<&'static MediaFormat> {
+    let extension = extension.to_lowercase();
+    MEDIA_FORMATS.iter().find(|format| format.extensions.contains(&extension.as_str()))
+  }
+
+  /// Returns the format of the file based on its extension.
+  pub fn find_format_by_path(file_path: &str) -> Option<&'static MediaFormat> {
+    Path::new(file_path)
+      .extension()
+      .and_then(std::ffi::OsStr::to_str)
+      .and_then(find_format)
+  }
+
+  /// Returns all extensions of the media type (or all extensions if it's `None`),
+  /// joined for use in a regular expression.
+  ///
+  /// ## Usage:
+  /// ```
+  /// use drag_and_crop::utils::format::format::{ get_extension_pattern, MediaType };
+  ///
+  /// let pattern = get_extension_pattern(Some(MediaType::Video));
+  /// assert_eq!(pattern, "mp4|mkv|flv|avi|mov|wmv|webm");
+  /// ```
+  pub fn get_extension_pattern(media_type: Option<MediaType>) -> String {
+    MEDIA_FORMATS.iter()
+      .filter(|format| media_type.is_none() || media_type == Some(format.media_type))
+      .flat_map(|format| format.extensions.iter().copied())
+      .collect::<Vec<&str>>()
+      .join("|")
+  }
+}
@@ -0,0 +1,69 @@
+pub mod frames {
+  use std::{ fs, path::Path };
+
+  use crate::utils::cancel::cancel::CancelToken;
+  use crate::utils::ffmpeg::ffmpeg::run_ffmpeg;
+  use crate::utils::progress::progress::ProgressParser;
+
+  /// Returns the size of the analysed frames - the longer side is scaled to `longer_side`,
+  /// the other one keeps the aspect ratio and is rounded to an even number.
+  ///
+  /// ## Usage:
+  /// ```
+  /// use drag_and_crop::utils::frames::frames::get_analysis_size;
+  ///
+  /// assert_eq!(get_analysis_size((1920, 1080), 160), (160, 90));
+  /// assert_eq!(get_analysis_size((1080, 1920), 160), (90, 160));
+  /// ```
+  pub fn get_analysis_size((width, height): (u32, u32), longer_side: u32) -> (u32, u32) {
+    if width >= height {
+      (longer_side, ((longer_side * height) / width).max(2) & !1)
+    } else {
+      (((longer_side * width) / height).max(2) & !1, longer_side)
+    }
+  }
+
+  /// Samples small grayscale frames of the video for analysis.
+  /// The frames are decoded to a temporary file at `frames_path`, which is removed afterwards.
+  pub fn read_gray_frames(
+    input_file_path: &str,
+    (start, duration): (Option<f64>, Option<f64>),
+    (width, height): (u32, u32),
+    (sample_rate, max_frames): (f64, u32),
+    frames_path: &Path,
+    cancel: &CancelToken
+  ) -> Result<Vec<Vec<u8>>, String> {
+    let mut args = Vec::new();
+    if let Some(start) = start {
+      args.extend([String::from("-ss"), start.to_string()]);
+    }
+    args.extend([String::from("-i"), String::from(input_file_path)]);
+    if let Some(duration) = duration {
+      args.extend([String::from("-t"), duration.to_string()]);
+    }
+    args.extend([
+      String::from("-an"),
+      String::from("-filter:v"),
+      format!("fps={},scale={}:{},format=gray", sample_rate, width, height),
+      String::from("-frames:v"),
+      max_frames.to_string(),
+      String::from("-f"),
+      String::from("rawvideo"),
+      frames_path.to_string_lossy().to_string(),
+    ]);
+    let result = run_ffmpeg(&args, ProgressParser::new(None, None), cancel, |_| ());
+    let bytes = result.and_then(|_| {
+      fs::read(frames_path).map_err(|error| format!("There was an error while analysing the video: {}", error))
+    });
+    let _ = fs::remove_file(frames_path);
+
+    let frames: Vec<Vec<u8>> = bytes?
+      .chunks_exact((width * height) as usize)
+      .map(|frame| frame.to_vec())
+      .collect();
+    if frames.is_empty() {
+      return Err(String::from("The video frames could not be analysed."));
+    }
+    Ok(frames)
+  }
+}
@@ -0,0 +1,226 @@
+pub mod hls {
+  use std::{ fs, path::{ Path, PathBuf } };
+
+  use crate::{ HlsFiles, HlsOptions, HlsRendition };
+  use crate::utils::cancel::cancel::CancelToken;
+  use crate::utils::ffmpeg::ffmpeg::{ get_ffmpeg, run_ffmpeg };
+  use crate::utils::file::file::append_to_file_name;
+  use crate::utils::probe::probe::probe_video;
+  use crate::utils::progress::progress::ProgressParser;
+
+  pub const DEFAULT_SEGMENT_DURATION: f64 = 6.0;
+  pub const MASTER_PLAYLIST_NAME: &str = "master.m3u8";
+  /// Heights and video bitrates (kbit/s) of the default ladder, renditions taller than the video are skipped.
+  const DEFAULT_LADDER: [(u32, u32); 4] = [
+    (1080, 5000),
+    (720, 2800),
+    (480, 1400),
+    (360, 800),
+  ];
+  const AUDIO_BITRATE: &str = "128k";
+  /// Peak bitrate and buffer size relative to the average bitrate of a rendition.
+  const MAX_RATE_FACTOR: f64 = 1.07;
+  const BUFFER_SIZE_FACTOR: f64 = 1.5;
+
+  /// Returns the folder with the HLS files of the video.
+  ///
+  /// ## Usage:
+  /// ```
+  /// use std::path::Path;
+  /// use drag_and_crop::utils::hls::hls::get_hls_directory;
+  ///
+  /// assert_eq!(get_hls_directory(Path::new("./tmp/video.mp4")).to_str().unwrap(), "./tmp/video-hls");
+  /// ```
+  pub fn get_hls_directory(video_file_path: &Path) -> PathBuf {
+    append_to_file_name(&video_file_path.with_extension(""), "-hls")
+  }
+
+  /// Returns the renditions of the ladder - the requested ones,
+  /// or the default renditions that aren't taller than the video (at least one).
+  ///
+  /// ## Usage:
+  /// ```
+  /// use drag_and_crop::utils::hls::hls::get_renditions;
+  /// use drag_and_crop::HlsOptions;
+  ///
+  /// let heights: Vec<u32> = get_renditions(&HlsOptions::default(), 720)
+  ///   .iter()
+  ///   .map(|rendition| rendition.height)
+  ///   .collect();
+  /// assert_eq!(heights, vec![720, 480, 360]);
+  /// assert_eq!(get_renditions(&HlsOptions::default(), 240)[0].height, 240);
+  /// ```
+  pub fn get_renditions(options: &HlsOptions, video_height: u32) -> Vec<HlsRendition> {
+    if let Some(renditions) = &options.renditions {
+      return renditions.clone();
+    }
+    let renditions: Vec<HlsRendition> = DEFAULT_LADDER
+      .iter()
+      .filter(|(height, _)| *height <= video_height)
+      .map(|(height, bitrate)| HlsRendition { height: *height, bitrate: Some(*bitrate) })
+      .collect();
+    if renditions.is_empty() {
+      let (_, bitrate) = DEFAULT_LADDER[DEFAULT_LADDER.len() - 1];
+      return vec![HlsRendition { height: video_height & !1, bitrate: Some(bitrate) }];
+    }
+    renditions
+  }
+
+  /// Checks the HLS options against the detected ffmpeg
+  /// and the height of the cropped video, if it's known before cropping.
+  pub fn check_hls_options(options: &HlsOptions, video_height: Option<u32>) -> Result<(), String> {
+    if let Some(segment_duration) = options.segment_duration {
+      if !segment_duration.is_finite() || segment_duration <= 0.0 {
+        return Err(String::from("The segment duration must be larger than 0."));
+      }
+    }
+    if let Some(renditions) = &options.renditions {
+      if renditions.is_empty() {
+        return Err(String::from("At least one HLS rendition is required."));
+      }
+      for rendition in renditions {
+        if rendition.height < 2 || rendition.height % 2 != 0 {
+          return Err(format!("The rendition height {} must be an even number.", rendition.height));
+        }
+        if video_height.is_some_and(|video_height| rendition.height > video_height) {
+          return Err(format!("The rendition height {} is larger than the cropped video.", rendition.height));
+        }
+        if rendition.bitrate == Some(0) {
+          return Err(String::from("The rendition bitrate must be larger than 0."));
+        }
+      }
+    }
+
+    let ffmpeg = get_ffmpeg()?;
+    if !ffmpeg.video_encoders.iter().any(|name| name == "libx264") {
+      return Err(String::from("HLS packaging requires the libx264 encoder."));
+    }
+    if !ffmpeg.audio_encoders.iter().any(|name| name == "aac") {
+      return Err(String::from("HLS packaging requires the aac audio encoder."));
+    }
+
+    Ok(())
+  }
+
+  /// Encodes the (cropped) video to the renditions of the ladder and writes the segments,
+  /// their playlists and the master playlist to a new folder next to the video.
+  /// The folder is deleted if the packaging fails.
+  pub fn package_hls(video_file_path: &Path, options: &HlsOptions, cancel: &CancelToken) -> Result<HlsFiles, String> {
+    let directory = get_hls_directory(video_file_path);
+    if directory.exists() {
+      return Err(String::from("The HLS folder already exists."));
+    }
+    if let Err(error) = fs::create_dir_all(&directory) {
+      return Err(format!("There was an error while creating the HLS folder: {}", error));
+    }
+    let result = write_hls(video_file_path, &directory, options, cancel);
+    if result.is_err() {
+      let _ = fs::remove_dir_all(&directory);
+    }
+    result
+  }
+
+  fn write_hls(
+    video_file_path: &Path,
+    directory: &Path,
+    options: &HlsOptions,
+    cancel: &CancelToken
+  ) -> Result<HlsFiles, String> {
+    let input_file_path = video_file_path.to_string_lossy().to_string();
+    let video = probe_video(&input_file_path, cancel)?;
+    let (_, video_height) = video.get_display_size();
+    check_hls_options(options, Some(video_height))?;
+    let renditions = get_renditions(options, video_height);
+    let has_audio = !video.audio_tracks.is_empty();
+    let segment_duration = options.segment_duration.unwrap_or(DEFAULT_SEGMENT_DURATION);
+
+    // 1) one scaled copy of the video per rendition
+    let outputs: Vec<String> = (0..renditions.len()).map(|index| format!("[s{}]", index)).collect();
+    let mut filter = format!("[0:v]split={}{}", renditions.len(), outputs.concat());
+    for (index, rendition) in renditions.iter().enumerate() {
+      filter.push_str(&format!(";[s{}]scale=-2:{}[v{}]", index, rendition.height, index));
+    }
+    let mut args = vec![
+      String::from("-i"),
+      input_file_path,
+      String::from("-filter_complex"),
+      filter,
+    ];
+    for index in 0..renditions.len() {
+      args.extend([String::from("-map"), format!("[v{}]", index)]);
+    }
+    if has_audio {
+      for _ in 0..renditions.len() {
+        args.extend([String::from("-map"), String::from("0:a:0")]);
+      }
+    }
+
+    // 2) codecs - the keyframes are forced at the segment boundaries, so that all renditions can be switched
+    args.extend(
+      [
+        "-c:v",
+        "libx264",
+        "-pix_fmt",
+        "yuv420p",
+        "-c:a",
+        "aac",
+        "-b:a",
+        AUDIO_BITRATE,
+        "-force_key_frames",
+      ].map(String::from)
+    );
+    args.push(format!("expr:gte(t,n_forced*{})", segment_duration));
+    for (index, rendition) in renditions.iter().enumerate() {
+      if let Some(bitrate) = rendition.bitrate {
+        args.extend([
+          format!("-b:v:{}", index),
+          format!("{}k", bitrate),
+          format!("-maxrate:v:{}", index),
+          format!("{}k", ((bitrate as f64) * MAX_RATE_FACTOR).round()),
+          format!("-bufsize:v:{}", index),
+          format!("{}k", ((bitrate as f64) * BUFFER_SIZE_FACTOR).round()),
+        ]);
+      }
+    }
+
+    // 3) playlists and segments
+    let stream_map: Vec<String> = (0..renditions.len())
+      .map(|index| if has_audio { format!("v:{},a:{}", index, index) } else { format!("v:{}", index) })
+      .collect();
+    args.extend([
+      String::from("-var_stream_map"),
+      stream_map.join(" "),
+      String::from("-f"),
+      String::from("hls"),
+      String::from("-hls_time"),
+      segment_duration.to_string(),
+      String::from("-hls_playlist_type"),
+      String::from("vod"),
+      String::from("-hls_flags"),
+      String::from("independent_segments"),
+      String::from("-hls_segment_filename"),
+      directory.join("stream_%v_%03d.ts").to_string_lossy().to_string(),
+      String::from("-master_pl_name"),
+      String::from(MASTER_PLAYLIST_NAME),
+      directory.join("stream_%v.m3u8").to_string_lossy().to_string(),
+    ]);
+    run_ffmpeg(&args, ProgressParser::new(None, None), cancel, |_| ())?;
+
+    let master_playlist_path = directory.join(MASTER_PLAYLIST_NAME);
+    if !master_playlist_path.exists() {
+      return Err(String::from("The HLS master playlist could not be created."));
+    }
+    let mut file_paths: Vec<String> = fs
+      ::read_dir(directory)
+      .map_err(|error| format!("There was an error while reading the HLS folder: {}", error))?
+      .filter_map(|entry| entry.ok())
+      .map(|entry| entry.path().to_string_lossy().to_string())
+      .collect();
+    file_paths.sort();
+
+    Ok(HlsFiles {
+      master_playlist_path: master_playlist_path.to_string_lossy().to_string(),
+      file_paths,
+    })
+  }
+}
@@ -0,0 +1,69 @@
+pub mod keyframes {
+  use crate::{ CropKeyframe, ImageSize, Interpolation, Point };
+
+  /// Returns the ffmpeg crop filter that moves the crop between the keyframes.
+  /// The size can't change over time, because ffmpeg evaluates it only once.
+  /// `offset` is the start of the trimmed video, as the filter timestamps start at 0.
+  ///
+  /// ## Usage:
+  /// ```
+  /// use drag_and_crop::utils::keyframes::keyframes::get_moving_crop_filter;
+  /// use drag_and_crop::{ CropKeyframe, ImageSize, Interpolation, Point };
+  ///
+  /// let keyframes = vec![
+  ///   CropKeyframe { time: 0.0, top_left_point: Point { x: 0, y: 10 }, interpolation: None },
+  ///   CropKeyframe { time: 2.0, top_left_point: Point { x: 100, y: 10 }, interpolation: None },
+  /// ];
+  /// let size = ImageSize { width: 360, height: 640 };
+  /// assert_eq!(
+  ///   get_moving_crop_filter(&size, &keyframes, 0.0),
+  ///   "crop=w=360:h=640:x='lt(t,0)*0+gte(t,0)*lt(t,2)*(0+(100)*(t-0)/2)+gte(t,2)*100':y='10'"
+  /// );
+  /// ```
+  pub fn get_moving_crop_filter(size: &ImageSize, keyframes: &[CropKeyframe], offset: f64) -> String {
+    format!(
+      "crop=w={}:h={}:x='{}':y='{}'",
+      size.width,
+      size.height,
+      get_axis_expression(keyframes, offset, |point| point.x),
+      get_axis_expression(keyframes, offset, |point| point.y)
+    )
+  }
+
+  /// Builds a sum of the segments between the keyframes, each one multiplied by a condition
+  /// that is only true during its segment. Unlike nested `if`s, this doesn't limit the number of keyframes.
+  fn get_axis_expression(keyframes: &[CropKeyframe], offset: f64, axis: impl Fn(&Point) -> u32) -> String {
+    let values: Vec<i64> = keyframes
+      .iter()
+      .map(|keyframe| axis(&keyframe.top_left_point) as i64)
+      .collect();
+    if values.windows(2).all(|pair| pair[0] == pair[1]) {
+      return values.first().copied().unwrap_or(0).to_string();
+    }
+    let time = if offset > 0.0 { format!("(t+{})", offset) } else { String::from("t") };
+
+    let mut terms = vec![format!("lt({},{})*{}", time, keyframes[0].time, values[0])];
+    for (index, pair) in keyframes.windows(2).enumerate() {
+      let (from, to) = (&pair[0], &pair[1]);
+      let (start, end) = (values[index], values[index + 1]);
+      let progress = format!("({}-{})/{}", time, from.time, to.time - from.time);
+      let segment = match from.interpolation.unwrap_or_default() {
+        Interpolation::Linear => format!("{}+({})*{}", start, end - start, progress),
+        Interpolation::Ease =>
+          format!(
+            "{}+({})*(3*pow({},2)-2*pow({},3))",
+            start,
+            end - start,
+            progress,
+            progress
+          ),
+        Interpolation::Hold => start.to_string(),
+      };
+      terms.push(format!("gte({},{})*lt({},{})*({})", time, from.time, time, to.time, segment));
+    }
+    let last = keyframes.len() - 1;
+    terms.push(format!("gte({},{})*{}", time, keyframes[last].time, values[last]));
+
+    terms.join("+")
+  }
+}
@@ -0,0 +1,208 @@
+pub mod limits {
+  use std::{ env, fs::File, io::BufReader };
+  use image::{ io::{ Limits, Reader }, error::LimitErrorKind, DynamicImage, ImageError };
+  use serde::{ Serialize, Deserialize };
+
+  pub const MAX_WIDTH_VARIABLE: &str = "DRAG_N_CROP_MAX_IMAGE_WIDTH";
+  pub const MAX_HEIGHT_VARIABLE: &str = "DRAG_N_CROP_MAX_IMAGE_HEIGHT";
+  pub const MAX_PIXELS_VARIABLE: &str = "DRAG_N_CROP_MAX_IMAGE_PIXELS";
+  pub const MAX_ALLOC_VARIABLE: &str = "DRAG_N_CROP_MAX_IMAGE_ALLOC";
+  pub const FFMPEG_TIMEOUT_VARIABLE: &str = "DRAG_N_CROP_FFMPEG_TIMEOUT";
+  pub const FFMPEG_THREADS_VARIABLE: &str = "DRAG_N_CROP_FFMPEG_THREADS";
+  pub const FFMPEG_NICE_VARIABLE: &str = "DRAG_N_CROP_FFMPEG_NICE";
+  pub const FFMPEG_MAX_ERROR_OUTPUT_VARIABLE: &str = "DRAG_N_CROP_FFMPEG_MAX_ERROR_OUTPUT";
+
+  /// Limits that protect the server from decompression bombs.
+  /// Images exceeding any of the limits are rejected before their pixels are decoded.
+  #[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+  pub struct ImageLimits {
+    pub max_width: u32,
+    pub max_height: u32,
+    pub max_pixels: u64,
+    /// Maximum number of bytes allocated for the decoded pixels.
+    pub max_alloc: u64,
+  }
+
+  impl Default for ImageLimits {
+    fn default() -> Self {
+      ImageLimits {
+        max_width: 20_000,
+        max_height: 20_000,
+        max_pixels: 200_000_000,
+        max_alloc: 1024 * 1024 * 1024,
+      }
+    }
+  }
+
+  impl ImageLimits {
+    /// Returns the default limits, overridden by the environment variables
+    /// `DRAG_N_CROP_MAX_IMAGE_WIDTH`, `DRAG_N_CROP_MAX_IMAGE_HEIGHT`,
+    /// `DRAG_N_CROP_MAX_IMAGE_PIXELS` and `DRAG_N_CROP_MAX_IMAGE_ALLOC` (bytes).
+    pub fn from_env() -> Self {
+      let defaults = ImageLimits::default();
+      ImageLimits {
+        max_width: read_variable(MAX_WIDTH_VARIABLE).unwrap_or(defaults.max_width),
+        max_height: read_variable(MAX_HEIGHT_VARIABLE).unwrap_or(defaults.max_height),
+        max_pixels: read_variable(MAX_PIXELS_VARIABLE).unwrap_or(defaults.max_pixels),
+        max_alloc: read_variable(MAX_ALLOC_VARIABLE).unwrap_or(defaults.max_alloc),
+      }
+    }
+
+    /// Checks image dimensions against the width, height and pixel count limits.
+    ///
+    /// ## Usage:
+    /// ```
+    /// use drag_and_crop::utils::limits::limits::ImageLimits;
+    ///
+    /// let limits = ImageLimits::default();
+    /// assert!(limits.check_dimensions(1920, 1080).is_ok());
+    /// assert_eq!(
+    ///   limits.check_dimensions(100_000, 100_000),
+    ///   Err(String::from("The image width (100000px) exceeds the limit of 20000px."))
+    /// );
+    /// ```
+    pub fn check_dimensions(&self, width: u32, height: u32) -> Result<(), String> {
+      if width > self.max_width {
+        return Err(
+          format!("The image width ({}px) exceeds the limit of {}px.", width, self.max_width)
+        );
+      }
+      if height > self.max_height {
+        return Err(
+          format!("The image height ({}px) exceeds the limit of {}px.", height, self.max_height)
+        );
+      }
+      let pixels = (width as u64) * (height as u64);
+      if pixels > self.max_pixels {
+        return Err(
+          format!(
+            "The image pixel count ({}) exceeds the limit of {} pixels.",
+            pixels,
+            self.max_pixels
+          )
+        );
+      }
+
+      Ok(())
+    }
+
+    /// Checks if the number of bytes can be allocated for decoding.
+    pub fn check_allocation(&self, bytes: u64) -> Result<(), String> {
+      if bytes > self.max_alloc {
+        return Err(
+          format!(
+            "Decoding the image needs {} bytes of memory, which exceeds the limit of {} bytes.",
+            bytes,
+            self.max_alloc
+          )
+        );
+      }
+
+      Ok(())
+    }
+
+    /// Converts the limits to the decoder limits of the `image` crate.
+    pub fn to_decoder_limits(&self) -> Limits {
+      let mut limits = Limits::default();
+      limits.max_image_width = Some(self.max_width);
+      limits.max_image_height = Some(self.max_height);
+      limits.max_alloc = Some(self.max_alloc);
+      limits
+    }
+  }
+
+  /// Limits of every ffmpeg process, so that a slow or malicious input can't occupy the server.
+  /// The timeout and the error output limit apply to ffprobe as well.
+  #[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+  pub struct ProcessLimits {
+    /// Wall-clock time (seconds) after which the process is killed, 0 means no limit.
+    pub timeout: u64,
+    /// Threads used for decoding, filtering and encoding, 0 lets ffmpeg decide.
+    pub threads: u32,
+    /// Scheduling priority of the process, from -20 (highest) to 19 (lowest). Only applied on Unix.
+    pub nice: i32,
+    /// Maximum number of bytes of the error output that are kept for the error message.
+    pub max_error_output: usize,
+  }
+
+  impl Default for ProcessLimits {
+    fn default() -> Self {
+      ProcessLimits {
+        timeout: 60 * 60,
+        threads: 0,
+        nice: 0,
+        max_error_output: 16 * 1024,
+      }
+    }
+  }
+
+  impl ProcessLimits {
+    /// Returns the default limits, overridden by the environment variables
+    /// `DRAG_N_CROP_FFMPEG_TIMEOUT` (seconds), `DRAG_N_CROP_FFMPEG_THREADS`,
+    /// `DRAG_N_CROP_FFMPEG_NICE` and `DRAG_N_CROP_FFMPEG_MAX_ERROR_OUTPUT` (bytes).
+    pub fn from_env() -> Self {
+      let defaults = ProcessLimits::default();
+      ProcessLimits {
+        timeout: read_variable(FFMPEG_TIMEOUT_VARIABLE).unwrap_or(defaults.timeout),
+        threads: read_variable(FFMPEG_THREADS_VARIABLE).unwrap_or(defaults.threads),
+        nice: read_variable::<i32>(FFMPEG_NICE_VARIABLE).unwrap_or(defaults.nice).clamp(-20, 19),
+        max_error_output: read_variable(FFMPEG_MAX_ERROR_OUTPUT_VARIABLE).unwrap_or(defaults.max_error_output),
+      }
+    }
+  }
+
+  fn read_variable<T: std::str::FromStr>(name: &str) -> Option<T> {
+    env::var(name).ok().and_then(|value| value.trim().parse().ok())
+  }
+
+  /// Opens the image while enforcing the limits.
+  /// The decoder limits don't include the pixel count, so the dimensions are read from the header
+  /// and checked before the pixels are decoded.
+  pub fn open_image(file_path: &str, limits: &ImageLimits) -> Result<DynamicImage, String> {
+    let (width, height) = match open_reader(file_path)?.into_dimensions() {
+      Ok(dimensions) => dimensions,
+      Err(error) => {
+        return Err(get_decoding_error_message(error, limits));
+      }
+    };
+    limits.check_dimensions(width, height)?;
+
+    // reading the dimensions consumed the reader
+    let mut reader = open_reader(file_path)?;
+    reader.limits(limits.to_decoder_limits());
+
+    match reader.decode() {
+      Ok(img) => Ok(img),
+      Err(error) => Err(get_decoding_error_message(error, limits)),
+    }
+  }
+
+  fn open_reader(file_path: &str) -> Result<Reader<BufReader<File>>, String> {
+    match Reader::open(file_path) {
+      Ok(reader) => Ok(reader),
+      Err(error) => Err(format!("There was an error while opening the image file: {:?}", error)),
+    }
+  }
+
+  /// Returns a readable message for errors that happen while decoding.
+  pub fn get_decoding_error_message(error: ImageError, limits: &ImageLimits) -> String {
+    match error {
+      ImageError::Limits(limit_error) =>
+        match limit_error.kind() {
+          LimitErrorKind::DimensionError =>
+            format!(
+              "The image dimensions exceed the limit of {}x{}px.",
+              limits.max_width,
+              limits.max_height
+            ),
+          LimitErrorKind::InsufficientMemory =>
+            format!(
+              "Decoding the image needs more memory than the limit of {} bytes.",
+              limits.max_alloc
+            ),
+          _ => format!("The image exceeds the decoding limits: {}", limit_error),
+        }
+      error => format!("There was an error while opening the image file: {:?}", error),
+    }
+  }
+}
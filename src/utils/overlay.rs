@@ -0,0 +1,144 @@
+pub mod overlay {
+  use image::{ imageops::{ self, FilterType }, ColorType, DynamicImage, GenericImageView };
+
+  use crate::{ Gravity, ImageSize, Point, Watermark };
+  use crate::utils::color::color::convert_to_color_type;
+  use crate::utils::limits::limits::{ open_image, ImageLimits };
+
+  pub const DEFAULT_OPACITY: f32 = 1.0;
+  pub const DEFAULT_SCALE: f32 = 0.2;
+
+  /// Returns the top left point of an overlay anchored to the base image by the gravity.
+  ///
+  /// ## Usage:
+  /// ```
+  /// use drag_and_crop::Gravity;
+  /// use drag_and_crop::utils::overlay::overlay::get_overlay_position;
+  ///
+  /// let point = get_overlay_position(Gravity::BottomRight, (1000, 500), (200, 100));
+  /// assert_eq!((point.x, point.y), (800, 400));
+  ///
+  /// let point = get_overlay_position(Gravity::Center, (1000, 500), (200, 100));
+  /// assert_eq!((point.x, point.y), (400, 200));
+  /// ```
+  pub fn get_overlay_position(
+    gravity: Gravity,
+    (base_width, base_height): (u32, u32),
+    (overlay_width, overlay_height): (u32, u32)
+  ) -> Point {
+    let right = base_width.saturating_sub(overlay_width);
+    let bottom = base_height.saturating_sub(overlay_height);
+    let (x, y) = match gravity {
+      Gravity::TopLeft => (0, 0),
+      Gravity::Top => (right / 2, 0),
+      Gravity::TopRight => (right, 0),
+      Gravity::Left => (0, bottom / 2),
+      Gravity::Center => (right / 2, bottom / 2),
+      Gravity::Right => (right, bottom / 2),
+      Gravity::BottomLeft => (0, bottom),
+      Gravity::Bottom => (right / 2, bottom),
+      Gravity::BottomRight => (right, bottom),
+    };
+
+    Point { x, y }
+  }
+
+  /// Returns the watermark size for the given base image size.
+  /// The width is `scale` times the base width and the aspect ratio is kept,
+  /// unless the watermark would end up taller than the base image.
+  ///
+  /// ## Usage:
+  /// ```
+  /// use drag_and_crop::utils::overlay::overlay::get_overlay_size;
+  ///
+  /// let size = get_overlay_size((1000, 500), (400, 100), 0.5);
+  /// assert_eq!((size.width, size.height), (500, 125));
+  ///
+  /// let size = get_overlay_size((1000, 100), (100, 100), 1.0);
+  /// assert_eq!((size.width, size.height), (100, 100));
+  /// ```
+  pub fn get_overlay_size(
+    (base_width, base_height): (u32, u32),
+    (overlay_width, overlay_height): (u32, u32),
+    scale: f32
+  ) -> ImageSize {
+    let aspect_ratio = (overlay_width as f64) / (overlay_height as f64);
+    let mut width = ((base_width as f64) * (scale as f64)).round();
+    let mut height = (width / aspect_ratio).round();
+    if height > (base_height as f64) {
+      height = base_height as f64;
+      width = (height * aspect_ratio).round();
+    }
+
+    ImageSize {
+      width: (width as u32).max(1),
+      height: (height as u32).max(1),
+    }
+  }
+
+  /// Composites the watermark onto the image.
+  /// The watermark file has to be available locally (`file_path`).
+  /// The image keeps its color type and bit depth.
+  pub fn apply_watermark(
+    img: DynamicImage,
+    watermark: &Watermark,
+    limits: &ImageLimits
+  ) -> Result<DynamicImage, String> {
+    let file_path = match &watermark.file_path {
+      Some(file_path) => file_path,
+      None => {
+        return Err(String::from("The watermark file path is missing."));
+      }
+    };
+    let watermark_img = open_image(file_path, limits)?.to_rgba8();
+
+    let scale = watermark.scale.unwrap_or(DEFAULT_SCALE);
+    let opacity = watermark.opacity.unwrap_or(DEFAULT_OPACITY);
+    let size = get_overlay_size(img.dimensions(), watermark_img.dimensions(), scale);
+    let mut resized = imageops::resize(
+      &watermark_img,
+      size.width,
+      size.height,
+      FilterType::Lanczos3
+    );
+    if opacity < 1.0 {
+      for pixel in resized.pixels_mut() {
+        pixel[3] = ((pixel[3] as f32) * opacity).round() as u8;
+      }
+    }
+
+    let position = match &watermark.position {
+      Some(point) => Point { x: point.x, y: point.y },
+      None =>
+        get_overlay_position(
+          watermark.gravity.unwrap_or_default(),
+          img.dimensions(),
+          resized.dimensions()
+        ),
+    };
+    let (x, y) = (position.x as i64, position.y as i64);
+
+    // blend in the highest precision of the image so that no samples are lost
+    let color_type = img.color();
+    let overlay = DynamicImage::ImageRgba8(resized);
+    let result = match color_type {
+      ColorType::L16 | ColorType::La16 | ColorType::Rgb16 | ColorType::Rgba16 => {
+        let mut base = img.into_rgba16();
+        imageops::overlay(&mut base, &overlay.to_rgba16(), x, y);
+        DynamicImage::ImageRgba16(base)
+      }
+      ColorType::Rgb32F | ColorType::Rgba32F => {
+        let mut base = img.into_rgba32f();
+        imageops::overlay(&mut base, &overlay.to_rgba32f(), x, y);
+        DynamicImage::ImageRgba32F(base)
+      }
+      _ => {
+        let mut base = img.into_rgba8();
+        imageops::overlay(&mut base, &overlay.to_rgba8(), x, y);
+        DynamicImage::ImageRgba8(base)
+      }
+    };
+
+    Ok(convert_to_color_type(result, color_type))
+  }
+}
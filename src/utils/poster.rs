@@ -0,0 +1,81 @@
+pub mod poster {
+  use std::path::Path;
+
+  use crate::utils::cancel::cancel::CancelToken;
+  use crate::utils::frames::frames::{ get_analysis_size, read_gray_frames };
+  use crate::utils::probe::probe::VideoInfo;
+
+  /// Extension of the poster format, if none is requested.
+  pub const DEFAULT_POSTER_FORMAT: &str = "jpg";
+  /// Length (px) of the longer side of the analysed frames - large enough to tell blurred frames apart.
+  const ANALYSIS_SIZE: u32 = 320;
+  const MAX_SAMPLE_RATE: f64 = 1.0;
+  const MAX_SAMPLES: u32 = 120;
+
+  /// Returns the sharpness of a grayscale frame - the variance of its Laplacian.
+  /// Blurred and flat frames (e.g. fades to black) have a low variance.
+  ///
+  /// ## Usage:
+  /// ```
+  /// use drag_and_crop::utils::poster::poster::get_sharpness;
+  ///
+  /// let flat = vec![128u8; 8 * 8];
+  /// let checkerboard: Vec<u8> = (0..64).map(|i| if (i % 8 + i / 8) % 2 == 0 { 0 } else { 255 }).collect();
+  /// assert_eq!(get_sharpness(&flat, (8, 8)), 0.0);
+  /// assert!(get_sharpness(&checkerboard, (8, 8)) > 0.0);
+  /// ```
+  pub fn get_sharpness(frame: &[u8], (width, height): (u32, u32)) -> f64 {
+    let (width, height) = (width as usize, height as usize);
+    if width < 3 || height < 3 {
+      return 0.0;
+    }
+    let mut sum = 0.0;
+    let mut sum_of_squares = 0.0;
+    for y in 1..height - 1 {
+      for x in 1..width - 1 {
+        let i = y * width + x;
+        let laplacian =
+          (frame[i - 1] as f64) +
+          (frame[i + 1] as f64) +
+          (frame[i - width] as f64) +
+          (frame[i + width] as f64) -
+          4.0 * (frame[i] as f64);
+        sum += laplacian;
+        sum_of_squares += laplacian * laplacian;
+      }
+    }
+    let count = ((width - 2) * (height - 2)) as f64;
+    let mean = sum / count;
+    sum_of_squares / count - mean * mean
+  }
+
+  /// Samples the video and returns the time (in seconds) of the sharpest frame.
+  pub fn find_sharpest_frame(
+    input_file_path: &str,
+    video: &VideoInfo,
+    frames_path: &Path,
+    cancel: &CancelToken
+  ) -> Result<f64, String> {
+    let size = get_analysis_size(video.get_display_size(), ANALYSIS_SIZE);
+    let sample_rate = match video.duration {
+      Some(duration) if duration > 0.0 => MAX_SAMPLE_RATE.min((MAX_SAMPLES as f64) / duration),
+      _ => MAX_SAMPLE_RATE,
+    };
+    let frames = read_gray_frames(
+      input_file_path,
+      (None, None),
+      size,
+      (sample_rate, MAX_SAMPLES),
+      frames_path,
+      cancel
+    )?;
+
+    let (index, _) = frames
+      .iter()
+      .map(|frame| get_sharpness(frame, size))
+      .enumerate()
+      .fold((0, f64::MIN), |best, current| if current.1 > best.1 { current } else { best });
+    let time = (index as f64) / sample_rate;
+    Ok((time * 1000.0).round() / 1000.0)
+  }
+}
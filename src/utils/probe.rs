@@ -0,0 +1,295 @@
+pub mod probe {
+  use serde::{ Serialize, Deserialize };
+  use serde_json::Value;
+
+  use crate::utils::cancel::cancel::CancelToken;
+  use crate::utils::ffmpeg::ffmpeg::run_ffprobe;
+
+  /// Properties of the first video stream of a file, and the other streams that are kept when it's cropped.
+  #[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+  pub struct VideoInfo {
+    /// Coded width of the frames.
+    pub width: u32,
+    /// Coded height of the frames.
+    pub height: u32,
+    /// Clockwise rotation that has to be applied to display the frames (0, 90, 180 or 270).
+    pub rotation: u32,
+    /// Duration in seconds.
+    pub duration: Option<f64>,
+    /// Frames per second.
+    pub frame_rate: Option<f64>,
+    /// Number of frames, if the container stores it.
+    pub frame_count: Option<u64>,
+    /// Sample (pixel) aspect ratio as (width, height).
+    pub sample_aspect_ratio: Option<(u32, u32)>,
+    /// Name of the codec, e.g. "h264".
+    pub codec_name: Option<String>,
+    /// Profile of the codec, e.g. "High".
+    pub profile: Option<String>,
+    /// Level of the codec as reported by ffprobe, e.g. 41 for h264 level 4.1.
+    pub level: Option<u32>,
+    /// Pixel format of the decoded frames, e.g. "yuv420p".
+    pub pixel_format: Option<String>,
+    /// Time base of the stream timestamps as (numerator, denominator).
+    pub time_base: Option<(u32, u32)>,
+    pub audio_tracks: Vec<AudioTrack>,
+    /// Codec names of the subtitle streams, empty for unknown codecs.
+    pub subtitle_codecs: Vec<String>,
+    /// Codec names of the attachments (e.g. fonts of the subtitles), empty for unknown codecs.
+    pub attachment_codecs: Vec<String>,
+  }
+
+  /// Properties of an audio stream.
+  #[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+  pub struct AudioTrack {
+    /// Index among the audio streams of the file (the N in `0:a:N`).
+    pub index: u32,
+    pub codec_name: Option<String>,
+    pub channels: Option<u32>,
+    pub language: Option<String>,
+  }
+
+  impl VideoInfo {
+    /// Returns the size of the frames as they are displayed (after rotation, in square pixels).
+    /// Crop coordinates are given in this space.
+    ///
+    /// ## Usage:
+    /// ```
+    /// use drag_and_crop::utils::probe::probe::VideoInfo;
+    ///
+    /// let video = VideoInfo { width: 1920, height: 1080, rotation: 90, ..Default::default() };
+    /// assert_eq!(video.get_display_size(), (1080, 1920));
+    ///
+    /// // anamorphic PAL DVD
+    /// let video = VideoInfo { width: 720, height: 576, sample_aspect_ratio: Some((16, 11)), ..Default::default() };
+    /// assert_eq!(video.get_display_size(), (1047, 576));
+    /// ```
+    pub fn get_display_size(&self) -> (u32, u32) {
+      // the sample aspect ratio stretches the coded width, before the rotation
+      let width = match self.sample_aspect_ratio {
+        Some((num, den)) if !self.has_square_pixels() =>
+          (((self.width as u64) * (num as u64) + (den as u64) / 2) / (den as u64)) as u32,
+        _ => self.width,
+      };
+      if self.rotation % 180 == 90 { (self.height, width) } else { (width, self.height) }
+    }
+
+    /// Returns the size of the frames that reach the ffmpeg filters.
+    /// ffmpeg rotates the frames before filtering (and removes the rotation from the output),
+    /// but keeps the pixels of the coded frame, which aren't square in anamorphic videos.
+    ///
+    /// ## Usage:
+    /// ```
+    /// use drag_and_crop::utils::probe::probe::VideoInfo;
+    ///
+    /// let video = VideoInfo { width: 720, height: 576, rotation: 270, sample_aspect_ratio: Some((16, 11)), ..Default::default() };
+    /// assert_eq!(video.get_frame_size(), (576, 720));
+    /// assert_eq!(video.get_display_size(), (576, 1047));
+    /// ```
+    pub fn get_frame_size(&self) -> (u32, u32) {
+      if self.rotation % 180 == 90 { (self.height, self.width) } else { (self.width, self.height) }
+    }
+
+    /// Returns `false` for anamorphic videos, whose pixels are stretched when displayed.
+    pub fn has_square_pixels(&self) -> bool {
+      match self.sample_aspect_ratio {
+        Some((num, den)) => num == 0 || den == 0 || num == den,
+        None => true,
+      }
+    }
+  }
+
+  /// Reads the properties of all streams with ffprobe (once per file).
+  pub fn probe_video(file_path: &str, cancel: &CancelToken) -> Result<VideoInfo, String> {
+    let output = run_ffprobe(&["-v", "error", "-show_streams", "-show_format", "-of", "json", file_path], cancel)?;
+    if !output.status.success() {
+      return Err(String::from("The input file is not a valid video."));
+    }
+
+    parse_probe_output(&output.output)
+  }
+
+  /// Reads the timestamps (in seconds) of the video keyframes between `from` and `to`.
+  /// Only the packets are read, so this is fast even for long videos.
+  pub fn probe_keyframes(file_path: &str, from: f64, to: f64, cancel: &CancelToken) -> Result<Vec<f64>, String> {
+    let interval = format!("{}%{}", from.max(0.0), to);
+    let output = run_ffprobe(
+      &["-v", "error", "-select_streams", "v:0", "-show_entries", "packet=pts_time,flags", "-of", "csv=p=0", "-read_intervals", &interval, file_path],
+      cancel
+    )?;
+    if !output.status.success() {
+      return Err(String::from("The keyframes of the video could not be read."));
+    }
+
+    Ok(parse_keyframes(&output.output))
+  }
+
+  /// Parses the keyframe timestamps from the output of
+  /// `ffprobe -show_entries packet=pts_time,flags -of csv=p=0`.
+  /// The timestamps are sorted, because packets are stored in decoding order.
+  ///
+  /// ## Usage:
+  /// ```
+  /// use drag_and_crop::utils::probe::probe::parse_keyframes;
+  ///
+  /// let output = "4.004000,K__\n4.037367,___\n8.341667,K__\nN/A,K__\n";
+  /// assert_eq!(parse_keyframes(output), vec![4.004, 8.341667]);
+  /// ```
+  pub fn parse_keyframes(output: &str) -> Vec<f64> {
+    let mut keyframes: Vec<f64> = output
+      .lines()
+      .filter_map(|line| {
+        let (time, flags) = line.trim().split_once(',')?;
+        if flags.contains('K') { time.parse().ok() } else { None }
+      })
+      .collect();
+    keyframes.sort_by(f64::total_cmp);
+    keyframes
+  }
+
+  /// Parses the JSON output of `ffprobe -show_streams -show_format -of json`.
+  ///
+  /// ## Usage:
+  /// ```
+  /// use drag_and_crop::utils::probe::probe::parse_probe_output;
+  ///
+  /// let output = r#"{
+  ///   "streams": [{
+  ///     "codec_type": "video", "codec_name": "h264", "width": 1920, "height": 1080,
+  ///     "r_frame_rate": "30000/1001", "sample_aspect_ratio": "1:1", "nb_frames": "300",
+  ///     "profile": "High", "level": 40, "pix_fmt": "yuv420p", "time_base": "1/30000",
+  ///     "side_data_list": [{ "side_data_type": "Display Matrix", "rotation": -90 }]
+  ///   }, {
+  ///     "codec_type": "audio", "codec_name": "aac", "channels": 2, "tags": { "language": "eng" }
+  ///   }, {
+  ///     "codec_type": "audio", "codec_name": "ac3", "channels": 6
+  ///   }, {
+  ///     "codec_type": "subtitle", "codec_name": "subrip"
+  ///   }, {
+  ///     "codec_type": "attachment"
+  ///   }],
+  ///   "format": { "duration": "10.010000" }
+  /// }"#;
+  /// let video = parse_probe_output(output).unwrap();
+  /// assert_eq!((video.width, video.height, video.rotation), (1920, 1080, 90));
+  /// assert_eq!(video.duration, Some(10.01));
+  /// assert_eq!(video.frame_count, Some(300));
+  /// assert_eq!(video.sample_aspect_ratio, Some((1, 1)));
+  /// assert_eq!(video.codec_name.as_deref(), Some("h264"));
+  /// assert_eq!((video.profile.as_deref(), video.level), (Some("High"), Some(40)));
+  /// assert_eq!((video.pixel_format.as_deref(), video.time_base), (Some("yuv420p"), Some((1, 30000))));
+  /// assert!((video.frame_rate.unwrap() - 29.97).abs() < 0.01);
+  /// assert_eq!(video.audio_tracks.len(), 2);
+  /// assert_eq!((video.audio_tracks[1].index, video.audio_tracks[1].channels), (1, Some(6)));
+  /// assert_eq!(video.audio_tracks[0].language.as_deref(), Some("eng"));
+  /// assert_eq!(video.subtitle_codecs, vec![String::from("subrip")]);
+  /// assert_eq!(video.attachment_codecs, vec![String::new()]);
+  /// ```
+  pub fn parse_probe_output(output: &str) -> Result<VideoInfo, String> {
+    let probe: Value = match serde_json::from_str(output) {
+      Ok(probe) => probe,
+      Err(_) => {
+        return Err(String::from("The input file is not a valid video."));
+      }
+    };
+    let streams = probe["streams"].as_array().cloned().unwrap_or_default();
+    let stream = match streams.iter().find(|stream| stream["codec_type"] == "video") {
+      Some(stream) => stream,
+      None => {
+        return Err(String::from("The input file does not contain a video stream."));
+      }
+    };
+
+    let width = stream["width"].as_u64().unwrap_or(0) as u32;
+    let height = stream["height"].as_u64().unwrap_or(0) as u32;
+    if width == 0 || height == 0 {
+      return Err(String::from("The video dimensions could not be read."));
+    }
+
+    let duration = parse_number(&stream["duration"]).or_else(|| parse_number(&probe["format"]["duration"]));
+    let frame_rate = stream["r_frame_rate"].as_str().and_then(|rate| parse_ratio(rate, '/')).and_then(
+      |(numerator, denominator)| {
+        if denominator == 0 || numerator == 0 {
+          None
+        } else {
+          Some((numerator as f64) / (denominator as f64))
+        }
+      }
+    );
+    let frame_count = stream["nb_frames"].as_str().and_then(|count| count.parse().ok());
+    let sample_aspect_ratio = stream["sample_aspect_ratio"]
+      .as_str()
+      .and_then(|ratio| parse_ratio(ratio, ':'))
+      .filter(|(numerator, denominator)| *numerator != 0 && *denominator != 0);
+
+    Ok(VideoInfo {
+      width,
+      height,
+      rotation: parse_rotation(stream),
+      duration,
+      frame_rate,
+      frame_count,
+      sample_aspect_ratio,
+      codec_name: stream["codec_name"].as_str().map(String::from),
+      profile: stream["profile"].as_str().map(String::from),
+      // unknown levels are reported as -99
+      level: stream["level"].as_i64().filter(|level| *level > 0).map(|level| level as u32),
+      pixel_format: stream["pix_fmt"].as_str().map(String::from),
+      time_base: stream["time_base"]
+        .as_str()
+        .and_then(|time_base| parse_ratio(time_base, '/'))
+        .filter(|(numerator, denominator)| *numerator != 0 && *denominator != 0),
+      audio_tracks: get_streams(&streams, "audio")
+        .enumerate()
+        .map(|(index, stream)| AudioTrack {
+          index: index as u32,
+          codec_name: stream["codec_name"].as_str().map(String::from),
+          channels: stream["channels"].as_u64().map(|channels| channels as u32),
+          language: stream["tags"]["language"].as_str().map(String::from),
+        })
+        .collect(),
+      subtitle_codecs: get_stream_codecs(&streams, "subtitle"),
+      attachment_codecs: get_stream_codecs(&streams, "attachment"),
+    })
+  }
+
+  /// Returns the streams of the type ("audio", "subtitle", ...) in their order in the file.
+  fn get_streams<'a>(streams: &'a [Value], codec_type: &'a str) -> impl Iterator<Item = &'a Value> {
+    streams.iter().filter(move |stream| stream["codec_type"] == codec_type)
+  }
+
+  /// Returns the codec names of the streams of the type, streams with an unknown codec have an empty name.
+  fn get_stream_codecs(streams: &[Value], codec_type: &str) -> Vec<String> {
+    get_streams(streams, codec_type)
+      .map(|stream| stream["codec_name"].as_str().unwrap_or_default().to_string())
+      .collect()
+  }
+
+  /// Reads the rotation from the display matrix (newer ffmpeg versions)
+  /// or the "rotate" tag (older versions) and converts it to clockwise degrees.
+  fn parse_rotation(stream: &Value) -> u32 {
+    let display_matrix = stream["side_data_list"]
+      .as_array()
+      .and_then(|side_data| side_data.iter().find_map(|data| data["rotation"].as_f64()));
+    let degrees = match display_matrix {
+      // the display matrix rotation is counterclockwise
+      Some(rotation) => -rotation,
+      None => parse_number(&stream["tags"]["rotate"]).unwrap_or(0.0),
+    };
+
+    ((((degrees / 90.0).round() as i64) * 90).rem_euclid(360)) as u32
+  }
+
+  fn parse_number(value: &Value) -> Option<f64> {
+    match value {
+      Value::String(text) => text.parse().ok(),
+      Value::Number(number) => number.as_f64(),
+      _ => None,
+    }
+  }
+
+  fn parse_ratio(text: &str, separator: char) -> Option<(u32, u32)> {
+    let (numerator, denominator) = text.split_once(separator)?;
+    Some((numerator.parse().ok()?, denominator.parse().ok()?))
+  }
+}
@@ -0,0 +1,113 @@
+pub mod progress {
+  use serde::{ Serialize, Deserialize };
+
+  /// Progress of an ffmpeg job.
+  #[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+  pub struct VideoProgress {
+    /// Value between 0 and 100.
+    pub percent: f64,
+    /// Number of processed frames.
+    pub frame: u64,
+    /// Processed frames per second.
+    pub fps: f64,
+    /// Processing speed relative to the playback speed.
+    pub speed: f64,
+    /// Position of the output in seconds.
+    pub out_time: f64,
+    /// Estimated remaining time in seconds.
+    pub eta: Option<f64>,
+  }
+
+  /// Parses the key-value output of `ffmpeg -progress`.
+  /// Every block of values ends with a "progress" key.
+  pub struct ProgressParser {
+    duration: Option<f64>,
+    frame_count: Option<u64>,
+    current: VideoProgress,
+  }
+
+  impl ProgressParser {
+    /// The percentage is based on the duration,
+    /// or on the frame count if the duration is unknown.
+    pub fn new(duration: Option<f64>, frame_count: Option<u64>) -> Self {
+      ProgressParser {
+        duration: duration.filter(|duration| *duration > 0.0),
+        frame_count: frame_count.filter(|frame_count| *frame_count > 0),
+        current: VideoProgress::default(),
+      }
+    }
+
+    /// Parses one line and returns the progress once a block is complete.
+    ///
+    /// ## Usage:
+    /// ```
+    /// use drag_and_crop::utils::progress::progress::ProgressParser;
+    ///
+    /// let mut parser = ProgressParser::new(Some(10.0), None);
+    /// assert_eq!(parser.parse_line("frame=75"), None);
+    /// assert_eq!(parser.parse_line("fps=25.00"), None);
+    /// assert_eq!(parser.parse_line("out_time_us=2500000"), None);
+    /// assert_eq!(parser.parse_line("speed=1.25x"), None);
+    /// let progress = parser.parse_line("progress=continue").unwrap();
+    /// assert_eq!(progress.frame, 75);
+    /// assert_eq!(progress.percent, 25.0);
+    /// assert_eq!(progress.eta, Some(6.0));
+    ///
+    /// let progress = parser.parse_line("progress=end").unwrap();
+    /// assert_eq!(progress.percent, 100.0);
+    /// ```
+    pub fn parse_line(&mut self, line: &str) -> Option<VideoProgress> {
+      let (key, value) = line.trim().split_once('=')?;
+      let value = value.trim();
+      match key {
+        "frame" => {
+          self.current.frame = value.parse().unwrap_or(self.current.frame);
+        }
+        "fps" => {
+          self.current.fps = value.parse().unwrap_or(self.current.fps);
+        }
+        "speed" => {
+          self.current.speed = value.trim_end_matches('x').parse().unwrap_or(self.current.speed);
+        }
+        // "out_time_ms" is in microseconds as well
+        "out_time_us" | "out_time_ms" => {
+          if let Ok(microseconds) = value.parse::<i64>() {
+            self.current.out_time = (microseconds.max(0) as f64) / 1_000_000.0;
+          }
+        }
+        "progress" => {
+          if value == "end" {
+            self.current.percent = 100.0;
+            self.current.eta = Some(0.0);
+          } else {
+            self.update_estimates();
+          }
+          return Some(self.current.clone());
+        }
+        _ => (),
+      }
+
+      None
+    }
+
+    fn update_estimates(&mut self) {
+      let ratio = match (self.duration, self.frame_count) {
+        (Some(duration), _) => Some(self.current.out_time / duration),
+        (None, Some(frame_count)) => Some((self.current.frame as f64) / (frame_count as f64)),
+        (None, None) => None,
+      };
+      let Some(ratio) = ratio else {
+        return;
+      };
+      self.current.percent = (ratio.clamp(0.0, 1.0) * 10000.0).round() / 100.0;
+
+      self.current.eta = match self.duration {
+        Some(duration) if self.current.speed > 0.0 => {
+          let remaining = (duration - self.current.out_time).max(0.0) / self.current.speed;
+          Some((remaining * 10.0).round() / 10.0)
+        }
+        _ => None,
+      };
+    }
+  }
+}
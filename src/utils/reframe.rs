@@ -0,0 +1,273 @@
+pub mod reframe {
+  use std::path::Path;
+
+  use crate::{ CropKeyframe, CropParameters, ImageSize, Interpolation, Point, ReframeAspect };
+  use crate::utils::cancel::cancel::CancelToken;
+  use crate::utils::file::file::append_to_file_name;
+  use crate::utils::frames::frames::{ get_analysis_size, read_gray_frames };
+  use crate::utils::probe::probe::VideoInfo;
+
+  /// Length (px) of the longer side of the analysed frames.
+  const ANALYSIS_SIZE: u32 = 160;
+  /// Frames sampled per second, reduced for long videos so that at most `MAX_SAMPLES` are analysed.
+  const MAX_SAMPLE_RATE: f64 = 2.0;
+  const MAX_SAMPLES: u32 = 600;
+  /// Motion is a stronger hint of the subject than edges.
+  const MOTION_WEIGHT: i32 = 2;
+  /// Share of the average score that is added at the center, so that flat frames stay centered.
+  const CENTER_BIAS: f64 = 0.1;
+  /// Number of samples in the median filter that removes single-frame jumps.
+  const MEDIAN_WINDOW: usize = 5;
+  /// Weight of a new sample in the exponential smoothing of the path.
+  const SMOOTHING_FACTOR: f64 = 0.3;
+
+  /// Returns the largest size with the aspect ratio that fits into the frame.
+  /// The dimensions are even, as most encoders require.
+  ///
+  /// ## Usage:
+  /// ```
+  /// use drag_and_crop::utils::reframe::reframe::get_reframe_size;
+  /// use drag_and_crop::ReframeAspect;
+  ///
+  /// let size = get_reframe_size((1920, 1080), ReframeAspect::Vertical);
+  /// assert_eq!((size.width, size.height), (606, 1080));
+  ///
+  /// let size = get_reframe_size((1080, 1920), ReframeAspect::Square);
+  /// assert_eq!((size.width, size.height), (1080, 1080));
+  /// ```
+  pub fn get_reframe_size((width, height): (u32, u32), aspect: ReframeAspect) -> ImageSize {
+    let (ratio_width, ratio_height) = match aspect {
+      ReframeAspect::Vertical => (9, 16),
+      ReframeAspect::Square => (1, 1),
+    };
+    if (width as u64) * ratio_height > (height as u64) * ratio_width {
+      let window_width = ((height as u64) * ratio_width / ratio_height) as u32;
+      ImageSize { width: window_width & !1, height: height & !1 }
+    } else {
+      let window_height = ((width as u64) * ratio_height / ratio_width) as u32;
+      ImageSize { width: width & !1, height: window_height & !1 }
+    }
+  }
+
+  /// Finds the most interesting window position in every frame.
+  /// Every pixel is scored by its edges (saliency) and its difference to the previous frame (motion),
+  /// the scores are summed along the sliding axis and the window with the highest sum wins.
+  ///
+  /// `frames` are grayscale frames of the given size, `window` is the window length along the axis.
+  /// Returns the window start of each frame.
+  ///
+  /// ## Usage:
+  /// ```
+  /// use drag_and_crop::utils::reframe::reframe::find_window_positions;
+  ///
+  /// // a bright square moving from the left to the right edge of a 16x4 frame
+  /// let frames: Vec<Vec<u8>> = [1, 12]
+  ///   .iter()
+  ///   .map(|left| {
+  ///     let mut frame = vec![0u8; 16 * 4];
+  ///     for y in 1..3 {
+  ///       for x in *left..*left + 2 {
+  ///         frame[y * 16 + x] = 255;
+  ///       }
+  ///     }
+  ///     frame
+  ///   })
+  ///   .collect();
+  /// let positions = find_window_positions(&frames, (16, 4), 6, true);
+  /// assert!(positions[0] <= 1.0);
+  /// assert!(positions[1] >= 8.0);
+  /// ```
+  pub fn find_window_positions(
+    frames: &[Vec<u8>],
+    (width, height): (u32, u32),
+    window: u32,
+    horizontal: bool
+  ) -> Vec<f64> {
+    let (width, height) = (width as usize, height as usize);
+    let length = if horizontal { width } else { height };
+    let window = (window as usize).clamp(1, length);
+
+    frames
+      .iter()
+      .enumerate()
+      .map(|(index, frame)| {
+        // the first frame is compared with the second one
+        let other = if index > 0 { frames.get(index - 1) } else { frames.get(1) };
+        let mut profile = vec![0.0; length];
+        for y in 0..height {
+          for x in 0..width {
+            let i = y * width + x;
+            let value = frame[i] as i32;
+            let mut score = 0;
+            if x + 1 < width {
+              score += (frame[i + 1] as i32 - value).abs();
+            }
+            if y + 1 < height {
+              score += (frame[i + width] as i32 - value).abs();
+            }
+            if let Some(other) = other {
+              score += MOTION_WEIGHT * (other[i] as i32 - value).abs();
+            }
+            profile[if horizontal { x } else { y }] += score as f64;
+          }
+        }
+
+        let center = (length as f64 - 1.0) / 2.0;
+        let bias = (profile.iter().sum::<f64>() / (length as f64)) * CENTER_BIAS;
+        for (position, score) in profile.iter_mut().enumerate() {
+          let distance = ((position as f64) - center).abs() / center.max(1.0);
+          *score += bias * (1.0 - distance);
+        }
+
+        // sliding window sum
+        let mut sum: f64 = profile[..window].iter().sum();
+        let (mut best_sum, mut best_start) = (sum, 0);
+        for start in 1..=length - window {
+          sum += profile[start + window - 1] - profile[start - 1];
+          if sum > best_sum {
+            best_sum = sum;
+            best_start = start;
+          }
+        }
+        best_start as f64
+      })
+      .collect()
+  }
+
+  /// Removes single-frame jumps with a median filter
+  /// and smooths the path in both directions, so that it doesn't lag behind the subject.
+  pub fn smooth_path(positions: &[f64]) -> Vec<f64> {
+    let radius = MEDIAN_WINDOW / 2;
+    let mut path: Vec<f64> = (0..positions.len())
+      .map(|index| {
+        let from = index.saturating_sub(radius);
+        let to = (index + radius + 1).min(positions.len());
+        let mut window = positions[from..to].to_vec();
+        window.sort_by(f64::total_cmp);
+        window[window.len() / 2]
+      })
+      .collect();
+
+    for index in 1..path.len() {
+      path[index] = path[index - 1] + SMOOTHING_FACTOR * (path[index] - path[index - 1]);
+    }
+    for index in (0..path.len().saturating_sub(1)).rev() {
+      path[index] = path[index + 1] + SMOOTHING_FACTOR * (path[index] - path[index + 1]);
+    }
+    path
+  }
+
+  /// Keeps only the points that are needed to follow the path within `tolerance` (Douglas-Peucker).
+  ///
+  /// ## Usage:
+  /// ```
+  /// use drag_and_crop::utils::reframe::reframe::simplify_path;
+  ///
+  /// let points = vec![(0.0, 0.0), (1.0, 10.0), (2.0, 20.0), (3.0, 20.0), (4.0, 20.5)];
+  /// assert_eq!(simplify_path(&points, 1.0), vec![(0.0, 0.0), (2.0, 20.0), (4.0, 20.5)]);
+  /// ```
+  pub fn simplify_path(points: &[(f64, f64)], tolerance: f64) -> Vec<(f64, f64)> {
+    if points.len() <= 2 {
+      return points.to_vec();
+    }
+    let (first, last) = (points[0], points[points.len() - 1]);
+    let (index, distance) = points[1..points.len() - 1]
+      .iter()
+      .enumerate()
+      .map(|(index, (time, value))| {
+        let progress = (time - first.0) / (last.0 - first.0);
+        (index + 1, (value - (first.1 + (last.1 - first.1) * progress)).abs())
+      })
+      .fold((0, 0.0), |best, current| if current.1 > best.1 { current } else { best });
+
+    if distance <= tolerance {
+      return vec![first, last];
+    }
+    let mut result = simplify_path(&points[..=index], tolerance);
+    result.pop();
+    result.extend(simplify_path(&points[index..], tolerance));
+    result
+  }
+
+  /// Analyses the video and returns the crop size and the keyframes of a crop that follows the subject.
+  pub fn auto_reframe(
+    options: &CropParameters,
+    video: &VideoInfo,
+    aspect: ReframeAspect,
+    output_file_path: &Path,
+    cancel: &CancelToken
+  ) -> Result<(ImageSize, Vec<CropKeyframe>), String> {
+    let (width, height) = video.get_display_size();
+    let size = get_reframe_size((width, height), aspect);
+    let center = Point { x: (width - size.width) / 2, y: (height - size.height) / 2 };
+    let horizontal = size.width < width;
+    if !horizontal && size.height >= height {
+      // the frame already has the aspect ratio
+      let keyframe = CropKeyframe { time: options.start.unwrap_or(0.0), top_left_point: center, interpolation: None };
+      return Ok((size, vec![keyframe]));
+    }
+
+    // 1) sample small grayscale frames
+    let (analysis_width, analysis_height) = get_analysis_size((width, height), ANALYSIS_SIZE);
+    let start = options.start.unwrap_or(0.0);
+    let duration = options
+      .get_trim_duration()
+      .or_else(|| video.duration.map(|duration| duration - start));
+    let sample_rate = match duration {
+      Some(duration) if duration > 0.0 => MAX_SAMPLE_RATE.min((MAX_SAMPLES as f64) / duration),
+      _ => MAX_SAMPLE_RATE,
+    };
+    let frames = read_gray_frames(
+      &options.input_file_path,
+      (options.start, options.get_trim_duration()),
+      (analysis_width, analysis_height),
+      (sample_rate, MAX_SAMPLES),
+      &append_to_file_name(&output_file_path.with_extension("gray"), "-frames"),
+      cancel
+    )?;
+
+    // 2) score the windows and smooth the path
+    let (length, analysis_length, window) = if horizontal {
+      (width, analysis_width, size.width)
+    } else {
+      (height, analysis_height, size.height)
+    };
+    let scale = (length as f64) / (analysis_length as f64);
+    let analysis_window = ((window as f64) / scale).round() as u32;
+    let positions = find_window_positions(
+      &frames,
+      (analysis_width, analysis_height),
+      analysis_window,
+      horizontal
+    );
+    let max_position = (length - window) as f64;
+    let points: Vec<(f64, f64)> = smooth_path(&positions)
+      .iter()
+      .enumerate()
+      .map(|(index, position)| {
+        (start + (index as f64) / sample_rate, (position * scale).clamp(0.0, max_position))
+      })
+      .collect();
+
+    // 3) convert the path to keyframes
+    let tolerance = ((length as f64) * 0.01).max(2.0);
+    let keyframes = simplify_path(&points, tolerance)
+      .iter()
+      .map(|(time, position)| {
+        let position = position.round() as u32;
+        let top_left_point = if horizontal {
+          Point { x: position, y: center.y }
+        } else {
+          Point { x: center.x, y: position }
+        };
+        CropKeyframe {
+          time: (time * 1000.0).round() / 1000.0,
+          top_left_point,
+          interpolation: Some(Interpolation::Linear),
+        }
+      })
+      .collect();
+
+    Ok((size, keyframes))
+  }
+}
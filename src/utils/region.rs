@@ -0,0 +1,216 @@
+pub mod region {
+  use std::{ fs::File, io::BufReader };
+  use image::{ DynamicImage, ImageBuffer, ImageFormat };
+  use png::{ BitDepth, Transformations };
+  use jpeg_decoder::PixelFormat;
+
+  use crate::{ CropDimensions, ImageSize };
+  use crate::utils::limits::limits::ImageLimits;
+
+  /// Decodes only the cropped region of the image, so that the memory usage depends on the
+  /// crop size instead of the size of the whole image.
+  /// - PNG: rows are streamed and only the ones inside the region are kept
+  /// - JPEG: the image is decoded at a reduced scale if it gets resized to a smaller size anyway
+  ///
+  /// Returns `None` if there's no region-oriented decoder for the image.
+  /// JPEG images can be returned scaled down, so they need to be resized to `target_size`.
+  pub fn open_region(
+    file_path: &str,
+    dimensions: &CropDimensions,
+    target_size: Option<&ImageSize>,
+    limits: &ImageLimits
+  ) -> Result<Option<DynamicImage>, String> {
+    match ImageFormat::from_path(file_path) {
+      Ok(ImageFormat::Png) => open_png_region(file_path, dimensions, limits),
+      Ok(ImageFormat::Jpeg) => {
+        match target_size {
+          Some(target_size) =>
+            open_scaled_jpeg_region(file_path, dimensions, target_size, limits),
+          None => Ok(None),
+        }
+      }
+      _ => Ok(None),
+    }
+  }
+
+  fn open_png_region(
+    file_path: &str,
+    dimensions: &CropDimensions,
+    limits: &ImageLimits
+  ) -> Result<Option<DynamicImage>, String> {
+    let file = match File::open(file_path) {
+      Ok(file) => file,
+      Err(error) => {
+        return Err(format!("There was an error while opening the image file: {:?}", error));
+      }
+    };
+    let mut decoder = png::Decoder::new(BufReader::new(file));
+    decoder.set_transformations(Transformations::EXPAND);
+    let mut reader = match decoder.read_info() {
+      Ok(reader) => reader,
+      Err(error) => {
+        return Err(format!("There was an error while opening the image file: {:?}", error));
+      }
+    };
+    // interlaced images are stored in passes, so the rows can't be streamed in order
+    if reader.info().interlaced {
+      return Ok(None);
+    }
+    limits.check_dimensions(reader.info().width, reader.info().height)?;
+
+    let (color_type, bit_depth) = reader.output_color_type();
+    let bytes_per_sample = if bit_depth == BitDepth::Sixteen { 2 } else { 1 };
+    let bytes_per_pixel = color_type.samples() * bytes_per_sample;
+    let x = dimensions.top_left_point.x as usize;
+    let y = dimensions.top_left_point.y as usize;
+    let width = dimensions.size.width as usize;
+    let height = dimensions.size.height as usize;
+    limits.check_allocation(
+      ((width * height + (reader.info().width as usize)) * bytes_per_pixel) as u64
+    )?;
+
+    let mut bytes = Vec::with_capacity(width * height * bytes_per_pixel);
+    let mut row_index = 0;
+    while row_index < y + height {
+      let row = match reader.next_row() {
+        Ok(Some(row)) => row,
+        Ok(None) => {
+          break;
+        }
+        Err(error) => {
+          return Err(format!("There was an error while reading the image file: {:?}", error));
+        }
+      };
+      if row_index >= y {
+        let data = row.data();
+        bytes.extend_from_slice(&data[x * bytes_per_pixel..(x + width) * bytes_per_pixel]);
+      }
+      row_index += 1;
+    }
+    if bytes.len() != width * height * bytes_per_pixel {
+      return Err(String::from("The image file is truncated."));
+    }
+
+    let (width, height) = (width as u32, height as u32);
+    let img = if bit_depth == BitDepth::Sixteen {
+      let samples: Vec<u16> = bytes
+        .chunks_exact(2)
+        .map(|sample| u16::from_be_bytes([sample[0], sample[1]]))
+        .collect();
+      match color_type {
+        png::ColorType::Grayscale =>
+          ImageBuffer::from_raw(width, height, samples).map(DynamicImage::ImageLuma16),
+        png::ColorType::GrayscaleAlpha =>
+          ImageBuffer::from_raw(width, height, samples).map(DynamicImage::ImageLumaA16),
+        png::ColorType::Rgb => ImageBuffer::from_raw(width, height, samples).map(DynamicImage::ImageRgb16),
+        png::ColorType::Rgba =>
+          ImageBuffer::from_raw(width, height, samples).map(DynamicImage::ImageRgba16),
+        png::ColorType::Indexed => None,
+      }
+    } else {
+      match color_type {
+        png::ColorType::Grayscale =>
+          ImageBuffer::from_raw(width, height, bytes).map(DynamicImage::ImageLuma8),
+        png::ColorType::GrayscaleAlpha =>
+          ImageBuffer::from_raw(width, height, bytes).map(DynamicImage::ImageLumaA8),
+        png::ColorType::Rgb => ImageBuffer::from_raw(width, height, bytes).map(DynamicImage::ImageRgb8),
+        png::ColorType::Rgba => ImageBuffer::from_raw(width, height, bytes).map(DynamicImage::ImageRgba8),
+        png::ColorType::Indexed => None,
+      }
+    };
+
+    Ok(img)
+  }
+
+  /// Returns the JPEG scale numerator (out of 8) that can be used to decode the image,
+  /// while keeping the cropped region at least as large as the target size.
+  ///
+  /// ## Usage:
+  /// ```
+  /// use drag_and_crop::ImageSize;
+  /// use drag_and_crop::utils::region::region::get_jpeg_scale;
+  ///
+  /// let crop = ImageSize { width: 4000, height: 3000 };
+  /// assert_eq!(get_jpeg_scale(&crop, &ImageSize { width: 500, height: 300 }), 1);
+  /// assert_eq!(get_jpeg_scale(&crop, &ImageSize { width: 1000, height: 1000 }), 4);
+  /// assert_eq!(get_jpeg_scale(&crop, &ImageSize { width: 5000, height: 100 }), 8);
+  /// ```
+  pub fn get_jpeg_scale(crop_size: &ImageSize, target_size: &ImageSize) -> u32 {
+    for scale in [1, 2, 4] {
+      if
+        (crop_size.width * scale) / 8 >= target_size.width &&
+        (crop_size.height * scale) / 8 >= target_size.height
+      {
+        return scale;
+      }
+    }
+
+    8
+  }
+
+  fn open_scaled_jpeg_region(
+    file_path: &str,
+    dimensions: &CropDimensions,
+    target_size: &ImageSize,
+    limits: &ImageLimits
+  ) -> Result<Option<DynamicImage>, String> {
+    let scale = get_jpeg_scale(&dimensions.size, target_size);
+    if scale == 8 {
+      return Ok(None);
+    }
+
+    let file = match File::open(file_path) {
+      Ok(file) => file,
+      Err(error) => {
+        return Err(format!("There was an error while opening the image file: {:?}", error));
+      }
+    };
+    let mut decoder = jpeg_decoder::Decoder::new(BufReader::new(file));
+    if let Err(error) = decoder.read_info() {
+      return Err(format!("There was an error while opening the image file: {:?}", error));
+    }
+    let info = decoder.info().unwrap();
+    limits.check_dimensions(info.width as u32, info.height as u32)?;
+    let requested_width = ((info.width as u32) * scale).div_ceil(8) as u16;
+    let requested_height = ((info.height as u32) * scale).div_ceil(8) as u16;
+    let (scaled_width, scaled_height) = match decoder.scale(requested_width, requested_height) {
+      Ok(size) => size,
+      Err(error) => {
+        return Err(format!("There was an error while reading the image file: {:?}", error));
+      }
+    };
+    limits.check_allocation(
+      (scaled_width as u64) * (scaled_height as u64) * (info.pixel_format.pixel_bytes() as u64)
+    )?;
+    let pixels = match decoder.decode() {
+      Ok(pixels) => pixels,
+      Err(error) => {
+        return Err(format!("There was an error while reading the image file: {:?}", error));
+      }
+    };
+
+    let (scaled_width, scaled_height) = (scaled_width as u32, scaled_height as u32);
+    let img = match info.pixel_format {
+      PixelFormat::L8 => ImageBuffer::from_raw(scaled_width, scaled_height, pixels).map(DynamicImage::ImageLuma8),
+      PixelFormat::RGB24 =>
+        ImageBuffer::from_raw(scaled_width, scaled_height, pixels).map(DynamicImage::ImageRgb8),
+      _ => None,
+    };
+    let img = match img {
+      Some(img) => img,
+      None => {
+        return Ok(None);
+      }
+    };
+
+    // map the crop region onto the scaled image
+    let scale_x = (scaled_width as f64) / (info.width as f64);
+    let scale_y = (scaled_height as f64) / (info.height as f64);
+    let x = ((dimensions.top_left_point.x as f64) * scale_x).floor() as u32;
+    let y = ((dimensions.top_left_point.y as f64) * scale_y).floor() as u32;
+    let width = ((dimensions.size.width as f64) * scale_x).round().max(1.0) as u32;
+    let height = ((dimensions.size.height as f64) * scale_y).round().max(1.0) as u32;
+
+    Ok(Some(img.crop_imm(x, y, width.min(scaled_width - x), height.min(scaled_height - y))))
+  }
+}
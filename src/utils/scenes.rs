@@ -0,0 +1,169 @@
+pub mod scenes {
+  use std::{ fs, path::{ Path, PathBuf } };
+
+  use crate::{ SceneBoundary, SceneParameters };
+  use crate::utils::cancel::cancel::CancelToken;
+  use crate::utils::ffmpeg::ffmpeg::run_ffmpeg;
+  use crate::utils::file::file::append_to_file_name;
+  use crate::utils::probe::probe::VideoInfo;
+  use crate::utils::progress::progress::ProgressParser;
+
+  pub const DEFAULT_THRESHOLD: f64 = 0.4;
+  pub const DEFAULT_THUMBNAIL_WIDTH: u32 = 160;
+  pub const DEFAULT_MAX_SCENES: u32 = 100;
+  /// Every cut is uploaded with its thumbnail, so the response stays reasonably small.
+  const MAX_SCENES: u32 = 1000;
+  const MAX_THUMBNAIL_WIDTH: u32 = 640;
+  /// JPEG quality of the thumbnails (2 - 31, lower is better).
+  const THUMBNAIL_QUALITY: &str = "5";
+
+  /// Returns the path of the thumbnail of the n-th cut (starting at 1).
+  ///
+  /// ## Usage:
+  /// ```
+  /// use std::path::Path;
+  /// use drag_and_crop::utils::scenes::scenes::get_scene_thumbnail_path;
+  ///
+  /// let path = get_scene_thumbnail_path(Path::new("./tmp/video.jpg"), 2);
+  /// assert_eq!(path.to_str().unwrap(), "./tmp/video-scene-002.jpg");
+  /// ```
+  pub fn get_scene_thumbnail_path(output_file_path: &Path, index: u32) -> PathBuf {
+    append_to_file_name(&output_file_path, &format!("-scene-{:03}", index))
+  }
+
+  /// Checks the threshold, thumbnail width and the number of cuts.
+  pub fn check_scene_options(options: &SceneParameters) -> Result<(), String> {
+    let threshold = options.threshold.unwrap_or(DEFAULT_THRESHOLD);
+    if !threshold.is_finite() || threshold <= 0.0 || threshold >= 1.0 {
+      return Err(String::from("The scene threshold must be between 0 and 1."));
+    }
+    if let Some(width) = options.thumbnail_width {
+      if !(2..=MAX_THUMBNAIL_WIDTH).contains(&width) {
+        return Err(format!("The thumbnail width must be between 2 and {}px.", MAX_THUMBNAIL_WIDTH));
+      }
+    }
+    if let Some(max_scenes) = options.max_scenes {
+      if !(1..=MAX_SCENES).contains(&max_scenes) {
+        return Err(format!("The maximum number of scenes must be between 1 and {}.", MAX_SCENES));
+      }
+    }
+
+    Ok(())
+  }
+
+  /// Parses the times and scores of the selected frames from the output of `metadata=print`.
+  ///
+  /// ## Usage:
+  /// ```
+  /// use drag_and_crop::utils::scenes::scenes::parse_scene_scores;
+  ///
+  /// let output = "frame:0    pts:60060   pts_time:2.002
+  /// lavfi.scene_score=0.523
+  /// frame:1    pts:150150  pts_time:5.005
+  /// lavfi.scene_score=0.871";
+  /// assert_eq!(parse_scene_scores(output), vec![(2.002, 0.523), (5.005, 0.871)]);
+  /// ```
+  pub fn parse_scene_scores(output: &str) -> Vec<(f64, f64)> {
+    let mut scores = Vec::new();
+    let mut time = None;
+    for line in output.lines() {
+      let line = line.trim();
+      if let Some(score) = line.strip_prefix("lavfi.scene_score=") {
+        if let (Some(time), Ok(score)) = (time.take(), score.parse()) {
+          scores.push((time, score));
+        }
+      } else if line.starts_with("frame:") {
+        time = line
+          .split_whitespace()
+          .find_map(|entry| entry.strip_prefix("pts_time:"))
+          .and_then(|value| value.parse().ok());
+      }
+    }
+    scores
+  }
+
+  /// Selects the frames that differ from the previous frame by more than the threshold
+  /// and saves them as thumbnails, in a single pass over the video.
+  pub fn find_scene_changes(
+    options: &SceneParameters,
+    video: &VideoInfo,
+    output_file_path: &Path,
+    cancel: &CancelToken
+  ) -> Result<Vec<SceneBoundary>, String> {
+    let max_scenes = options.max_scenes.unwrap_or(DEFAULT_MAX_SCENES);
+    let scores_path = append_to_file_name(&output_file_path.with_extension("txt"), "-scenes");
+    let result = write_scene_thumbnails(options, video, output_file_path, &scores_path, cancel);
+    let _ = fs::remove_file(&scores_path);
+
+    match result {
+      Ok(scores) => {
+        // the filter can select a few more frames than the output keeps
+        let scenes = scores
+          .into_iter()
+          .zip(1..=max_scenes)
+          .map(|((time, score), index)| SceneBoundary {
+            time,
+            score,
+            thumbnail: get_scene_thumbnail_path(output_file_path, index).to_string_lossy().to_string(),
+          })
+          .filter(|scene| Path::new(&scene.thumbnail).exists())
+          .collect();
+        Ok(scenes)
+      }
+      Err(error) => {
+        for index in 1..=max_scenes {
+          if fs::remove_file(get_scene_thumbnail_path(output_file_path, index)).is_err() {
+            break;
+          }
+        }
+        Err(error)
+      }
+    }
+  }
+
+  fn write_scene_thumbnails(
+    options: &SceneParameters,
+    video: &VideoInfo,
+    output_file_path: &Path,
+    scores_path: &Path,
+    cancel: &CancelToken
+  ) -> Result<Vec<(f64, f64)>, String> {
+    let threshold = options.threshold.unwrap_or(DEFAULT_THRESHOLD);
+    let (video_width, video_height) = video.get_display_size();
+    let width = options.thumbnail_width.unwrap_or(DEFAULT_THUMBNAIL_WIDTH);
+    let height = ((width * video_height) / video_width.max(1)).max(2) & !1;
+    // the colons of the path are escaped for the option parser and the filter graph parser
+    let scores_file = scores_path.to_string_lossy().replace('\\', "/").replace(':', "\\\\:");
+
+    let args = [
+      String::from("-i"),
+      options.input_file_path.clone(),
+      String::from("-an"),
+      String::from("-filter:v"),
+      format!(
+        "select='gt(scene,{})',metadata=print:file={},scale={}:{},setsar=1",
+        threshold,
+        scores_file,
+        width,
+        height
+      ),
+      String::from("-vsync"),
+      String::from("vfr"),
+      String::from("-frames:v"),
+      options.max_scenes.unwrap_or(DEFAULT_MAX_SCENES).to_string(),
+      String::from("-q:v"),
+      String::from(THUMBNAIL_QUALITY),
+      append_to_file_name(&output_file_path, "-scene-%03d").to_string_lossy().to_string(),
+    ];
+    run_ffmpeg(&args, ProgressParser::new(None, None), cancel, |_| ())?;
+
+    // the file is only written once a frame was selected
+    if !scores_path.exists() {
+      return Ok(Vec::new());
+    }
+    match fs::read_to_string(scores_path) {
+      Ok(output) => Ok(parse_scene_scores(&output)),
+      Err(error) => Err(format!("There was an error while reading the scene changes: {}", error)),
+    }
+  }
+}
@@ -0,0 +1,113 @@
+pub mod streams {
+  use crate::CropParameters;
+  use crate::utils::audio::audio::get_audio_map_args;
+  use crate::utils::probe::probe::VideoInfo;
+
+  /// Returns `true` if the container can store the subtitle codec without converting it.
+  ///
+  /// ## Usage:
+  /// ```
+  /// use drag_and_crop::utils::streams::streams::is_supported_subtitle_container;
+  ///
+  /// assert!(is_supported_subtitle_container("subrip", "mkv"));
+  /// assert!(is_supported_subtitle_container("mov_text", "mp4"));
+  /// assert!(!is_supported_subtitle_container("subrip", "mp4"));
+  /// assert!(!is_supported_subtitle_container("mov_text", "mkv"));
+  /// assert!(is_supported_subtitle_container("webvtt", "webm"));
+  /// ```
+  pub fn is_supported_subtitle_container(codec: &str, container: &str) -> bool {
+    match container {
+      // mov_text is specific to the mp4 family
+      "mkv" => !codec.is_empty() && codec != "mov_text",
+      "mp4" | "mov" => codec == "mov_text",
+      "webm" => codec == "webvtt",
+      _ => false,
+    }
+  }
+
+  /// Returns the ffmpeg arguments that map the streams of the input explicitly,
+  /// instead of relying on the default stream selection of ffmpeg:
+  /// - the first video stream
+  /// - the selected audio tracks (all by default)
+  /// - the subtitles and attachments that the container can store (copied)
+  /// - the container metadata and chapters, unless they are removed
+  ///
+  /// Also returns warnings about the streams that had to be dropped.
+  /// The streams are taken from the probed input, so no other ffprobe runs are needed.
+  pub fn get_stream_args(
+    options: &CropParameters,
+    video: &VideoInfo,
+    container: &str
+  ) -> Result<(Vec<String>, Vec<String>), String> {
+    let mut args = vec![String::from("-map"), String::from("0:v:0")];
+    args.extend(get_audio_map_args(options.audio.as_ref(), video)?);
+    let mut warnings = Vec::new();
+
+    // 1) subtitles
+    let subtitles = &video.subtitle_codecs;
+    let mut subtitle_count = 0;
+    for (index, codec) in subtitles.iter().enumerate() {
+      if is_supported_subtitle_container(codec, container) {
+        args.extend([String::from("-map"), format!("0:s:{}", index)]);
+        subtitle_count += 1;
+      }
+    }
+    if subtitle_count > 0 {
+      args.extend([String::from("-c:s"), String::from("copy")]);
+    }
+    if subtitle_count < subtitles.len() {
+      warnings.push(
+        format!(
+          "{} subtitle tracks can't be stored in the {} container and were removed.",
+          subtitles.len() - subtitle_count,
+          container
+        )
+      );
+    }
+
+    // 2) attachments (e.g. fonts of the subtitles) are only supported by matroska
+    let attachments = &video.attachment_codecs;
+    if !attachments.is_empty() {
+      if container == "mkv" {
+        args.extend([String::from("-map"), String::from("0:t"), String::from("-c:t"), String::from("copy")]);
+      } else {
+        warnings.push(
+          format!("{} attachments can't be stored in the {} container and were removed.", attachments.len(), container)
+        );
+      }
+    }
+
+    // 3) metadata and chapters
+    args.extend(get_metadata_args(options, 0));
+
+    Ok((args, warnings))
+  }
+
+  /// Returns the arguments that keep or remove the container metadata (e.g. the creation time)
+  /// and the chapters of the input with the index. Both are kept by default.
+  /// Only the global metadata is mapped, the stream metadata (e.g. the language of the audio tracks) is always kept.
+  ///
+  /// ## Usage:
+  /// ```
+  /// use drag_and_crop::utils::streams::streams::get_metadata_args;
+  /// use drag_and_crop::CropParameters;
+  ///
+  /// let options = CropParameters { keep_chapters: Some(false), ..Default::default() };
+  /// assert_eq!(get_metadata_args(&options, 0), vec!["-map_metadata:g", "0", "-map_chapters", "-1"]);
+  ///
+  /// let options = CropParameters { keep_metadata: Some(false), ..Default::default() };
+  /// assert_eq!(get_metadata_args(&options, 1), vec!["-map_metadata:g", "-1", "-map_chapters", "1"]);
+  /// ```
+  pub fn get_metadata_args(options: &CropParameters, input_index: u32) -> Vec<String> {
+    let get_source = |is_kept: Option<bool>| {
+      if is_kept.unwrap_or(true) { input_index.to_string() } else { String::from("-1") }
+    };
+    vec![
+      // "-map_metadata -1" would also remove the stream metadata
+      String::from("-map_metadata:g"),
+      get_source(options.keep_metadata),
+      String::from("-map_chapters"),
+      get_source(options.keep_chapters)
+    ]
+  }
+}
@@ -0,0 +1,244 @@
+pub mod thumbnails {
+  use std::{ fs, path::{ Path, PathBuf } };
+
+  use crate::{ ThumbnailFiles, ThumbnailOptions };
+  use crate::utils::cancel::cancel::CancelToken;
+  use crate::utils::ffmpeg::ffmpeg::run_ffmpeg;
+  use crate::utils::file::file::append_to_file_name;
+  use crate::utils::probe::probe::probe_video;
+  use crate::utils::progress::progress::ProgressParser;
+
+  pub const DEFAULT_INTERVAL: f64 = 2.0;
+  pub const DEFAULT_WIDTH: u32 = 160;
+  pub const DEFAULT_COLUMNS: u32 = 10;
+  /// Keeps the sprite small enough to be loaded by the player.
+  /// The JPEG size limit depends on the layout, so it's checked separately.
+  const MAX_THUMBNAILS: u32 = 1000;
+  const MAX_WIDTH: u32 = 640;
+  /// JPEG images can't be wider or higher than this (px).
+  const MAX_SPRITE_SIZE: u32 = 65535;
+  /// JPEG quality of the sprite (2 - 31, lower is better).
+  const SPRITE_QUALITY: &str = "5";
+
+  /// Returns the path of the sprite sheet that belongs to the video.
+  ///
+  /// ## Usage:
+  /// ```
+  /// use std::path::Path;
+  /// use drag_and_crop::utils::thumbnails::thumbnails::{ get_sprite_path, get_vtt_path };
+  ///
+  /// assert_eq!(get_sprite_path(Path::new("./tmp/video.mp4")).to_str().unwrap(), "./tmp/video-sprite.jpg");
+  /// assert_eq!(get_vtt_path(Path::new("./tmp/video.mp4")).to_str().unwrap(), "./tmp/video.vtt");
+  /// ```
+  pub fn get_sprite_path(video_file_path: &Path) -> PathBuf {
+    append_to_file_name(&video_file_path.with_extension("jpg"), "-sprite")
+  }
+
+  /// Returns the path of the WebVTT file that belongs to the video.
+  pub fn get_vtt_path(video_file_path: &Path) -> PathBuf {
+    video_file_path.with_extension("vtt")
+  }
+
+  /// Checks the thumbnail options against the duration and the size of the cropped video,
+  /// if they are known before cropping.
+  ///
+  /// ## Usage:
+  /// ```
+  /// use drag_and_crop::ThumbnailOptions;
+  /// use drag_and_crop::utils::thumbnails::thumbnails::check_thumbnail_options;
+  ///
+  /// let options = ThumbnailOptions { interval: Some(1.0), columns: Some(1), ..Default::default() };
+  /// assert!(check_thumbnail_options(&options, Some(60.0), Some((1920, 1080))).is_ok());
+  /// // 900 rows of 90px thumbnails
+  /// assert_eq!(
+  ///   check_thumbnail_options(&options, Some(900.0), Some((1920, 1080))),
+  ///   Err(String::from("The sprite would be 160x81000px, but JPEG images can't be larger than 65535px."))
+  /// );
+  /// ```
+  pub fn check_thumbnail_options(
+    options: &ThumbnailOptions,
+    duration: Option<f64>,
+    video_size: Option<(u32, u32)>
+  ) -> Result<(), String> {
+    let interval = options.interval.unwrap_or(DEFAULT_INTERVAL);
+    if !interval.is_finite() || interval <= 0.0 {
+      return Err(String::from("The thumbnail interval must be larger than 0."));
+    }
+    if let Some(width) = options.width {
+      if !(2..=MAX_WIDTH).contains(&width) {
+        return Err(format!("The thumbnail width must be between 2 and {}px.", MAX_WIDTH));
+      }
+    }
+    if options.columns == Some(0) {
+      return Err(String::from("The sprite must have at least one column."));
+    }
+    if let Some(duration) = duration {
+      if get_thumbnail_count(duration, interval) > MAX_THUMBNAILS {
+        return Err(
+          format!("The video is too long for {} thumbnails, the interval has to be larger.", MAX_THUMBNAILS)
+        );
+      }
+      if let Some(video_size) = video_size {
+        check_sprite_size(&get_sprite_layout(options, duration, video_size))?;
+      }
+    }
+
+    Ok(())
+  }
+
+  /// Size of a thumbnail, columns and rows of the sprite.
+  type SpriteLayout = ((u32, u32), u32, u32);
+
+  fn get_sprite_layout(
+    options: &ThumbnailOptions,
+    duration: f64,
+    (video_width, video_height): (u32, u32)
+  ) -> SpriteLayout {
+    let width = options.width.unwrap_or(DEFAULT_WIDTH);
+    let height = ((width * video_height) / video_width.max(1)).max(2) & !1;
+    let count = get_thumbnail_count(duration, options.interval.unwrap_or(DEFAULT_INTERVAL));
+    let columns = options.columns.unwrap_or(DEFAULT_COLUMNS).min(count);
+    ((width, height), columns, count.div_ceil(columns))
+  }
+
+  fn check_sprite_size(((width, height), columns, rows): &SpriteLayout) -> Result<(), String> {
+    let (sprite_width, sprite_height) = ((*columns as u64) * (*width as u64), (*rows as u64) * (*height as u64));
+    if sprite_width > (MAX_SPRITE_SIZE as u64) || sprite_height > (MAX_SPRITE_SIZE as u64) {
+      return Err(
+        format!(
+          "The sprite would be {}x{}px, but JPEG images can't be larger than {}px.",
+          sprite_width,
+          sprite_height,
+          MAX_SPRITE_SIZE
+        )
+      );
+    }
+
+    Ok(())
+  }
+
+  /// Returns the WebVTT file that maps the time ranges to the thumbnails of the sprite (in rows of `columns`).
+  ///
+  /// ## Usage:
+  /// ```
+  /// use drag_and_crop::utils::thumbnails::thumbnails::get_thumbnail_vtt;
+  ///
+  /// let vtt = get_thumbnail_vtt(3.5, 2.0, (160, 90), 10, "video-sprite.jpg");
+  /// assert_eq!(
+  ///   vtt,
+  ///   "WEBVTT\n\n\
+  ///   00:00:00.000 --> 00:00:02.000\nvideo-sprite.jpg#xywh=0,0,160,90\n\n\
+  ///   00:00:02.000 --> 00:00:03.500\nvideo-sprite.jpg#xywh=160,0,160,90\n"
+  /// );
+  /// ```
+  pub fn get_thumbnail_vtt(
+    duration: f64,
+    interval: f64,
+    (width, height): (u32, u32),
+    columns: u32,
+    sprite_url: &str
+  ) -> String {
+    let mut vtt = String::from("WEBVTT\n");
+    for index in 0..get_thumbnail_count(duration, interval) {
+      let start = (index as f64) * interval;
+      let end = (start + interval).min(duration);
+      let (x, y) = ((index % columns) * width, (index / columns) * height);
+      vtt.push_str(
+        &format!(
+          "\n{} --> {}\n{}#xywh={},{},{},{}\n",
+          format_vtt_time(start),
+          format_vtt_time(end),
+          sprite_url,
+          x,
+          y,
+          width,
+          height
+        )
+      );
+    }
+    vtt
+  }
+
+  /// Creates the sprite sheet and the WebVTT file of the (cropped) video.
+  /// The files are deleted if one of them can't be created.
+  pub fn create_thumbnails(
+    video_file_path: &Path,
+    options: &ThumbnailOptions,
+    cancel: &CancelToken
+  ) -> Result<ThumbnailFiles, String> {
+    let sprite_path = get_sprite_path(video_file_path);
+    let vtt_path = get_vtt_path(video_file_path);
+    let result = write_thumbnails(video_file_path, &sprite_path, &vtt_path, options, cancel);
+    if result.is_err() {
+      let _ = fs::remove_file(&sprite_path);
+      let _ = fs::remove_file(&vtt_path);
+    }
+    result?;
+
+    Ok(ThumbnailFiles {
+      sprite_file_path: sprite_path.to_string_lossy().to_string(),
+      vtt_file_path: vtt_path.to_string_lossy().to_string(),
+    })
+  }
+
+  fn write_thumbnails(
+    video_file_path: &Path,
+    sprite_path: &Path,
+    vtt_path: &Path,
+    options: &ThumbnailOptions,
+    cancel: &CancelToken
+  ) -> Result<(), String> {
+    let video = probe_video(&video_file_path.to_string_lossy(), cancel)?;
+    let duration = match video.duration {
+      Some(duration) if duration > 0.0 => duration,
+      _ => {
+        return Err(String::from("The thumbnails can't be created, the video duration is unknown."));
+      }
+    };
+    let interval = options.interval.unwrap_or(DEFAULT_INTERVAL);
+    // the size of the cropped video isn't known before cropping if the black bars were removed
+    let layout = get_sprite_layout(options, duration, video.get_display_size());
+    check_sprite_size(&layout)?;
+    let ((width, height), columns, rows) = layout;
+
+    // 1) sprite - the last tile is padded if the video ends before the grid is full
+    let args = [
+      String::from("-i"),
+      video_file_path.to_string_lossy().to_string(),
+      String::from("-an"),
+      String::from("-filter:v"),
+      format!("fps=1/{},scale={}:{},tile={}x{}", interval, width, height, columns, rows),
+      String::from("-frames:v"),
+      String::from("1"),
+      String::from("-q:v"),
+      String::from(SPRITE_QUALITY),
+      sprite_path.to_string_lossy().to_string(),
+    ];
+    run_ffmpeg(&args, ProgressParser::new(None, None), cancel, |_| ())?;
+
+    // 2) WebVTT file
+    let sprite_url = match &options.sprite_url {
+      Some(sprite_url) => sprite_url.clone(),
+      None => sprite_path.file_name().unwrap_or_default().to_string_lossy().to_string(),
+    };
+    let vtt = get_thumbnail_vtt(duration, interval, (width, height), columns, &sprite_url);
+    fs::write(vtt_path, vtt).map_err(|error|
+      format!("There was an error while saving the WebVTT file: {}", error)
+    )
+  }
+
+  fn get_thumbnail_count(duration: f64, interval: f64) -> u32 {
+    ((duration / interval).ceil() as u32).max(1)
+  }
+
+  fn format_vtt_time(seconds: f64) -> String {
+    let milliseconds = (seconds * 1000.0).round() as u64;
+    format!(
+      "{:02}:{:02}:{:02}.{:03}",
+      milliseconds / 3_600_000,
+      (milliseconds / 60_000) % 60,
+      (milliseconds / 1000) % 60,
+      milliseconds % 1000
+    )
+  }
+}
@@ -0,0 +1,351 @@
+pub mod trim {
+  use std::{ fs, path::{ Path, PathBuf } };
+
+  use crate::{ CropDimensions, CropParameters, TrimMode };
+  use crate::utils::cancel::cancel::CancelToken;
+  use crate::utils::file::file::append_to_file_name;
+  use crate::utils::ffmpeg::ffmpeg::{ get_ffmpeg, get_pixel_formats, run_ffmpeg };
+  use crate::utils::streams::streams::get_metadata_args;
+  use crate::utils::probe::probe::{ probe_keyframes, probe_video, VideoInfo };
+  use crate::utils::progress::progress::{ ProgressParser, VideoProgress };
+
+  /// Keyframes closer than this (in seconds) to the start time are treated as the start time.
+  const KEYFRAME_TOLERANCE: f64 = 0.001;
+  /// How far (in seconds) before and after the start time the keyframes are looked for.
+  const KEYFRAME_SEARCH_RANGE: f64 = 60.0;
+
+  /// Returns `true` if the crop rectangle covers the whole frame of the given size.
+  ///
+  /// ## Usage:
+  /// ```
+  /// use drag_and_crop::utils::trim::trim::is_full_frame;
+  /// use drag_and_crop::{ CropDimensions, ImageSize, Point };
+  ///
+  /// let dimensions = CropDimensions {
+  ///   top_left_point: Point { x: 0, y: 0 },
+  ///   size: ImageSize { width: 1920, height: 1080 },
+  /// };
+  /// assert!(is_full_frame(&dimensions, (1920, 1080)));
+  /// assert!(!is_full_frame(&dimensions, (1920, 1200)));
+  /// ```
+  pub fn is_full_frame(dimensions: &CropDimensions, (width, height): (u32, u32)) -> bool {
+    dimensions.top_left_point.x == 0 &&
+      dimensions.top_left_point.y == 0 &&
+      dimensions.size.width == width &&
+      dimensions.size.height == height
+  }
+
+  /// Trims the video with stream copy, so that (almost) nothing is re-encoded.
+  /// - `TrimMode::Keyframe` starts at the last keyframe before the start time,
+  ///   if there is one within `KEYFRAME_SEARCH_RANGE` seconds
+  /// - `TrimMode::Accurate` re-encodes the frames up to the first keyframe after the start time
+  ///   and copies the rest
+  ///
+  /// Returns the actual start and end times of the output,
+  /// or `None` if the video has to be re-encoded instead.
+  pub fn copy_trimmed(
+    options: &CropParameters,
+    video: &VideoInfo,
+    stream_args: &[String],
+    output_file_path: &Path,
+    cancel: &CancelToken,
+    mut on_progress: impl FnMut(&VideoProgress)
+  ) -> Result<Option<(f64, f64)>, String> {
+    let input_file_path = options.input_file_path.as_str();
+    let start = options.start.unwrap_or(0.0);
+    let end = options
+      .get_trim_duration()
+      .map(|duration| start + duration)
+      .or(video.duration);
+    let keyframes = if start > KEYFRAME_TOLERANCE {
+      probe_keyframes(
+        input_file_path,
+        start - KEYFRAME_SEARCH_RANGE,
+        start + KEYFRAME_SEARCH_RANGE,
+        cancel
+      )?
+    } else {
+      vec![0.0]
+    };
+
+    let actual_start = match options.trim_mode.unwrap_or_default() {
+      TrimMode::Keyframe => {
+        let keyframe = keyframes
+          .iter()
+          .rev()
+          .find(|keyframe| **keyframe <= start + KEYFRAME_TOLERANCE)
+          .copied();
+        // without a keyframe in the searched range, copying would start far too early (or at the beginning)
+        let keyframe = match keyframe {
+          Some(keyframe) => keyframe,
+          None => {
+            return Ok(None);
+          }
+        };
+        copy_segment((input_file_path, stream_args), keyframe, end, output_file_path, cancel, &mut on_progress)?;
+        keyframe
+      }
+      TrimMode::Accurate => {
+        let next_keyframe = keyframes
+          .iter()
+          .find(|keyframe| **keyframe >= start - KEYFRAME_TOLERANCE)
+          .copied();
+        match next_keyframe {
+          // the start time is a keyframe, so the whole range can be copied
+          Some(keyframe) if keyframe - start <= KEYFRAME_TOLERANCE => {
+            copy_segment((input_file_path, stream_args), keyframe, end, output_file_path, cancel, &mut on_progress)?;
+            keyframe
+          }
+          Some(keyframe) if end.is_none_or(|end| keyframe < end) => {
+            // the head has to match the copied tail, otherwise the whole range is re-encoded
+            let container = output_file_path
+              .extension()
+              .map(|extension| extension.to_string_lossy().to_lowercase())
+              .unwrap_or_default();
+            let encoding_args = match get_matching_encoding_args(video, &container) {
+              Some(encoding_args) if is_encoder_available(&encoding_args[1], video) => encoding_args,
+              _ => {
+                return Ok(None);
+              }
+            };
+            copy_with_encoded_head(
+              (options, stream_args),
+              (start, keyframe, end),
+              &encoding_args,
+              output_file_path,
+              cancel,
+              &mut on_progress
+            )?;
+            start
+          }
+          // there's no keyframe in the range, so there's nothing to copy
+          _ => {
+            return Ok(None);
+          }
+        }
+      }
+    };
+
+    // the copied packets don't end exactly at the end time, so the output is measured
+    let actual_end = match probe_video(&output_file_path.to_string_lossy(), cancel) {
+      Ok(output) if output.duration.is_some() => actual_start + output.duration.unwrap(),
+      _ => end.unwrap_or(actual_start),
+    };
+
+    Ok(Some((actual_start, actual_end)))
+  }
+
+  /// Re-encodes `start..keyframe` with the stream parameters of the input, copies `keyframe..end`
+  /// and joins both parts without re-encoding.
+  fn copy_with_encoded_head(
+    input: (&CropParameters, &[String]),
+    (start, keyframe, end): (f64, f64, Option<f64>),
+    encoding_args: &[String],
+    output_file_path: &Path,
+    cancel: &CancelToken,
+    on_progress: &mut impl FnMut(&VideoProgress)
+  ) -> Result<(), String> {
+    let parts = [
+      append_to_file_name(&output_file_path, "-head"),
+      append_to_file_name(&output_file_path, "-tail"),
+      append_to_file_name(&output_file_path.with_extension("txt"), "-parts"),
+    ];
+    let result = join_parts(
+      input,
+      (start, keyframe, end),
+      encoding_args,
+      &parts,
+      output_file_path,
+      cancel,
+      on_progress
+    );
+
+    // the parts are removed whether joining them succeeded or not
+    for path in &parts {
+      let _ = fs::remove_file(path);
+    }
+    result
+  }
+
+  fn join_parts(
+    (options, stream_args): (&CropParameters, &[String]),
+    (start, keyframe, end): (f64, f64, Option<f64>),
+    encoding_args: &[String],
+    [head_path, tail_path, list_path]: &[PathBuf; 3],
+    output_file_path: &Path,
+    cancel: &CancelToken,
+    on_progress: &mut impl FnMut(&VideoProgress)
+  ) -> Result<(), String> {
+    let input_file_path = options.input_file_path.as_str();
+    let mut args = get_range_args(input_file_path, start, Some(keyframe));
+    args.extend(stream_args.iter().cloned());
+    args.extend(encoding_args.iter().cloned());
+    args.extend(["-c:a", "copy"].map(String::from));
+    args.push(head_path.to_string_lossy().to_string());
+    run_ffmpeg(&args, ProgressParser::new(None, None), cancel, |_| ())?;
+
+    copy_segment((input_file_path, stream_args), keyframe, end, tail_path, cancel, on_progress)?;
+
+    // the concat demuxer resolves the paths relative to the list
+    let list = [head_path, tail_path]
+      .iter()
+      .map(|path| {
+        let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+        format!("file '{}'\n", file_name.replace('\'', "'\\''"))
+      })
+      .collect::<String>();
+    if let Err(error) = fs::write(list_path, list) {
+      return Err(format!("There was an error while processing the video: {}", error));
+    }
+    let mut args = ["-f", "concat", "-safe", "0", "-i", &list_path.to_string_lossy()].map(String::from).to_vec();
+    // the concat demuxer drops the metadata and chapters, so they are taken from the trimmed input
+    args.extend(["-ss", &start.to_string(), "-i", input_file_path].map(String::from));
+    args.extend(["-map", "0", "-c", "copy"].map(String::from));
+    args.extend(get_metadata_args(options, 1));
+    args.push(output_file_path.to_string_lossy().to_string());
+    run_ffmpeg(&args, ProgressParser::new(None, None), cancel, |_| ())
+  }
+
+  fn copy_segment(
+    (input_file_path, stream_args): (&str, &[String]),
+    start: f64,
+    end: Option<f64>,
+    output_file_path: &Path,
+    cancel: &CancelToken,
+    on_progress: &mut impl FnMut(&VideoProgress)
+  ) -> Result<(), String> {
+    let mut args = get_range_args(input_file_path, start, end);
+    args.extend(stream_args.iter().cloned());
+    args.extend(["-c", "copy", "-avoid_negative_ts", "make_zero"].map(String::from));
+    args.push(output_file_path.to_string_lossy().to_string());
+
+    let parser = ProgressParser::new(end.map(|end| end - start), None);
+    run_ffmpeg(&args, parser, cancel, on_progress)
+  }
+
+  fn get_range_args(input_file_path: &str, start: f64, end: Option<f64>) -> Vec<String> {
+    let mut args = Vec::new();
+    if start > 0.0 {
+      args.extend([String::from("-ss"), start.to_string()]);
+    }
+    args.extend([String::from("-i"), String::from(input_file_path)]);
+    if let Some(end) = end {
+      args.extend([String::from("-t"), (end - start).to_string()]);
+    }
+    args
+  }
+
+  /// Returns the encoding arguments that reproduce the codec, profile, level, pixel format
+  /// and time base of the video, so that a re-encoded part can be joined with copied parts of it.
+  /// Returns `None` if the parameters are unknown or can't be set for the encoder.
+  ///
+  /// ## Usage:
+  /// ```
+  /// use drag_and_crop::utils::probe::probe::VideoInfo;
+  /// use drag_and_crop::utils::trim::trim::get_matching_encoding_args;
+  ///
+  /// let video = VideoInfo {
+  ///   codec_name: Some(String::from("h264")),
+  ///   profile: Some(String::from("High")),
+  ///   level: Some(41),
+  ///   pixel_format: Some(String::from("yuv420p")),
+  ///   time_base: Some((1, 15360)),
+  ///   ..Default::default()
+  /// };
+  /// assert_eq!(
+  ///   get_matching_encoding_args(&video, "mp4").unwrap(),
+  ///   vec![
+  ///     "-c:v", "libx264", "-profile:v", "high", "-level:v", "4.1",
+  ///     "-pix_fmt", "yuv420p", "-video_track_timescale", "15360"
+  ///   ]
+  /// );
+  ///
+  /// // x264 can't encode the intra-only profiles
+  /// let video = VideoInfo { profile: Some(String::from("High 4:4:4 Intra")), ..video };
+  /// assert_eq!(get_matching_encoding_args(&video, "mp4"), None);
+  /// ```
+  pub fn get_matching_encoding_args(video: &VideoInfo, container: &str) -> Option<Vec<String>> {
+    let profile = video.profile.as_deref();
+    let mut args = match video.codec_name.as_deref()? {
+      "h264" => {
+        let profile = match profile? {
+          "Baseline" | "Constrained Baseline" => "baseline",
+          "Main" => "main",
+          "High" => "high",
+          "High 10" => "high10",
+          "High 4:2:2" => "high422",
+          "High 4:4:4 Predictive" => "high444",
+          _ => {
+            return None;
+          }
+        };
+        let level = video.level?;
+        vec![
+          String::from("-c:v"),
+          String::from("libx264"),
+          String::from("-profile:v"),
+          String::from(profile),
+          String::from("-level:v"),
+          format!("{}.{}", level / 10, level % 10)
+        ]
+      }
+      "hevc" => {
+        let profile = match profile? {
+          "Main" => "main",
+          "Main 10" => "main10",
+          "Main Still Picture" => "mainstillpicture",
+          _ => {
+            return None;
+          }
+        };
+        // the hevc level is reported multiplied by 30
+        let level = video.level?;
+        vec![
+          String::from("-c:v"),
+          String::from("libx265"),
+          String::from("-profile:v"),
+          String::from(profile),
+          String::from("-x265-params"),
+          format!("level-idc={}.{}", level / 30, (level % 30) / 3)
+        ]
+      }
+      "vp9" => {
+        let profile = profile?.strip_prefix("Profile ")?;
+        ["-c:v", "libvpx-vp9", "-profile:v", profile].map(String::from).to_vec()
+      }
+      // vp8 has a single profile and no levels
+      "vp8" => ["-c:v", "libvpx"].map(String::from).to_vec(),
+      _ => {
+        return None;
+      }
+    };
+    args.extend([String::from("-pix_fmt"), video.pixel_format.clone()?]);
+    // matroska always uses milliseconds, mp4 and mov keep the time base of the track
+    if container == "mp4" || container == "mov" {
+      match video.time_base? {
+        (1, timescale) => args.extend([String::from("-video_track_timescale"), timescale.to_string()]),
+        _ => {
+          return None;
+        }
+      }
+    }
+    Some(args)
+  }
+
+  /// Checks if the detected ffmpeg has the encoder and if it supports the pixel format of the video.
+  fn is_encoder_available(encoder: &str, video: &VideoInfo) -> bool {
+    let ffmpeg = match get_ffmpeg() {
+      Ok(ffmpeg) => ffmpeg,
+      Err(_) => {
+        return false;
+      }
+    };
+    if !ffmpeg.video_encoders.iter().any(|name| name == encoder) {
+      return false;
+    }
+    match (get_pixel_formats(encoder), &video.pixel_format) {
+      (Ok(formats), Some(pixel_format)) => formats.contains(pixel_format),
+      _ => false,
+    }
+  }
+}
@@ -1,8 +1,22 @@
 pub mod validation {
-  use std::path::Path;
-  use image::GenericImageView;
+  use std::path::{ Path, PathBuf };
 
-  use crate::{ CropParameters, utils::file::file::{ is_image_file, is_video_file } };
+  use crate::{ CropParameters, PosterParameters, SceneParameters, utils::file::file::{ is_image_file, is_video_file } };
+  use crate::utils::animation::animation::{ get_animation_args, get_animation_extension };
+  use crate::utils::audio::audio::get_audio_args;
+  use crate::utils::cancel::cancel::CancelToken;
+  use crate::utils::dimensions::dimensions::{ apply_dimension_policy, apply_dimension_policy_to_keyframes };
+  use crate::utils::encoding::encoding::{ get_container, get_encoding_args };
+  use crate::utils::hls::hls::{ check_hls_options, get_hls_directory };
+  use crate::utils::format::format::{ find_format, find_format_by_path, MediaType };
+  use crate::utils::limits::limits::ImageLimits;
+  use crate::utils::poster::poster::DEFAULT_POSTER_FORMAT;
+  use crate::utils::probe::probe::{ probe_video, VideoInfo };
+  use crate::utils::reframe::reframe::get_reframe_size;
+  use crate::utils::scenes::scenes::{ check_scene_options, get_scene_thumbnail_path };
+  use crate::utils::streams::streams::get_stream_args;
+  use crate::utils::thumbnails::thumbnails::{ check_thumbnail_options, get_sprite_path, get_vtt_path };
+  use crate::{ CropDimensions, CropKeyframe, ImageSize };
 
   /// Validates cropping parameters.
   /// If the validation is successful, it returns `true`, or an error message otherwise.
@@ -15,10 +29,11 @@ pub mod validation {
   /// let mut params = CropParameters {
   ///   input_file_path: String::from("./static/media/bird.jpg"),
   ///   output_file_path: Some(String::from("./static/media/bird-cropped.jpg")),
-  ///   dimensions: CropDimensions {
+  ///   dimensions: Some(CropDimensions {
   ///     size: ImageSize { width: 100, height: 100 },
   ///     top_left_point: Point { x: 50, y: 50 },
-  ///   },
+  ///   }),
+  ///   ..Default::default()
   /// };
   ///
   /// let mut result = validate_options(&params);
@@ -29,16 +44,24 @@ pub mod validation {
   /// assert_eq!(result, Err(String::from("The input file does not exist.")));
   ///
   /// params.input_file_path = String::from("./static/media/bird.jpg");
-  /// params.dimensions.top_left_point.x = 2000;
+  /// params.dimensions = Some(CropDimensions { top_left_point: Point { x: 2000, y: 50 }, ..params.dimensions.unwrap() });
   /// result = validate_options(&params);
   /// assert_eq!(result, Err(String::from("The top left point is out of bounds.")));
   ///
-  /// params.dimensions.top_left_point.x = 50;
-  /// params.dimensions.size.width = 5000;
+  /// params.dimensions = Some(CropDimensions {
+  ///   size: ImageSize { width: 5000, height: 100 },
+  ///   top_left_point: Point { x: 50, y: 50 },
+  /// });
   /// result = validate_options(&params);
   /// assert_eq!(result, Err(String::from("The output size is larger than the input image size.")));
   /// ```
   pub fn validate_options(options: &CropParameters) -> Result<bool, String> {
+    validate_crop_options(options, &CancelToken::new()).map(|_| true)
+  }
+
+  /// Validates cropping parameters like `validate_options` and returns the probed video stream (`None` for images),
+  /// so that the video doesn't have to be probed again while cropping.
+  pub fn validate_crop_options(options: &CropParameters, cancel: &CancelToken) -> Result<Option<VideoInfo>, String> {
     // 0) check if input file exists
     if !Path::new(&options.input_file_path).exists() {
       return Err(String::from("The input file does not exist."));
@@ -53,35 +76,425 @@ pub mod validation {
 
     // 2) check if output file exists
     if let Some(output_path) = &options.output_file_path {
-      if Path::new(&output_path).exists() {
+      let mut output_path = PathBuf::from(output_path);
+      if let Some(format) = &options.format {
+        output_path.set_extension(format.to_lowercase());
+      }
+      if let Some(encoding) = &options.encoding {
+        output_path.set_extension(get_container(Some(encoding), &options.input_file_path));
+      }
+      if let Some(animation) = &options.animation {
+        output_path.set_extension(get_animation_extension(animation.format));
+      }
+      if output_path.exists() {
         return Err(String::from("The output file already exists."));
       }
+      if options.thumbnails.is_some() && (get_sprite_path(&output_path).exists() || get_vtt_path(&output_path).exists()) {
+        return Err(String::from("The thumbnail files already exist."));
+      }
+      if options.hls.is_some() && get_hls_directory(&output_path).exists() {
+        return Err(String::from("The HLS folder already exists."));
+      }
     }
 
+    let mut probed_video = None;
     if is_image {
-      // 3) check if top left point is within image dimensions
-      let img = image::open(Path::new(&options.input_file_path));
-      if img.is_err() {
-        let err_message = img.err().unwrap().to_string();
+      // 3) check if the image format can be decoded
+      if let Some(format) = find_format_by_path(&options.input_file_path) {
+        if !format.can_decode {
+          return Err(format!("Cropping {} images is not supported.", format.extensions[0]));
+        }
+      }
+
+      // 4) check if top left point is within image dimensions
+      // only the header is read, the pixels are decoded while cropping
+      let img_dimensions = image::image_dimensions(Path::new(&options.input_file_path));
+      if img_dimensions.is_err() {
+        let err_message = img_dimensions.err().unwrap().to_string();
         return Err(err_message);
       }
-      let (width, height) = img.unwrap().dimensions();
-      if
-        options.dimensions.top_left_point.x >= width ||
-        options.dimensions.top_left_point.y >= height
-      {
-        return Err(String::from("The top left point is out of bounds."));
+      let (width, height) = img_dimensions.unwrap();
+      options.limits.unwrap_or_else(ImageLimits::from_env).check_dimensions(width, height)?;
+
+      // 5) check if output size if larger than input size
+      check_crop_bounds(&options.get_dimensions((width, height)), (width, height), "image")?;
+
+      if options.is_trimmed() || options.trim_mode.is_some() {
+        return Err(String::from("Trimming is only supported for videos."));
+      }
+      if options.encoding.is_some() {
+        return Err(String::from("Encoding options are only supported for videos."));
+      }
+      if options.audio.is_some() {
+        return Err(String::from("Audio options are only supported for videos."));
+      }
+      if options.keyframes.is_some() {
+        return Err(String::from("Crop keyframes are only supported for videos."));
+      }
+      if options.auto_reframe.is_some() {
+        return Err(String::from("Automatic reframing is only supported for videos."));
+      }
+      if options.animation.is_some() {
+        return Err(String::from("Animations can only be created from videos."));
+      }
+      if options.thumbnails.is_some() {
+        return Err(String::from("Thumbnails are only supported for videos."));
+      }
+      if options.hls.is_some() {
+        return Err(String::from("HLS packaging is only supported for videos."));
+      }
+      if options.dimension_policy.is_some() {
+        return Err(String::from("The dimension policy is only supported for videos."));
+      }
+      if options.keep_metadata.is_some() || options.keep_chapters.is_some() {
+        return Err(String::from("Keeping metadata and chapters is only supported for videos."));
+      }
+      if options.remove_black_bars.is_some() {
+        return Err(String::from("Black bars can only be removed from videos."));
+      }
+    } else {
+      // 3) probe the video stream and check the crop bounds against the displayed frame
+      // the crop of automatically reframed videos and videos with removed black bars is found while cropping
+      let video = probe_video(&options.input_file_path, cancel)?;
+      let dimensions = options.get_dimensions(video.get_display_size());
+      let is_detected = options.remove_black_bars == Some(true);
+      if is_detected && (options.auto_reframe.is_some() || options.keyframes.is_some()) {
+        return Err(String::from("Black bars can't be removed from reframed videos or with crop keyframes."));
+      }
+      if options.auto_reframe.is_none() && !is_detected {
+        check_crop_bounds(&dimensions, video.get_display_size(), "video")?;
+      } else if options.keyframes.is_some() {
+        return Err(String::from("Crop keyframes can't be combined with automatic reframing."));
+      }
+
+      // 4) check the trimmed range against the video duration
+      check_time_range(options, video.duration)?;
+      if let Some(keyframes) = &options.keyframes {
+        check_keyframes(keyframes, &dimensions.size, video.get_display_size(), video.duration)?;
+      }
+
+      // 5) check if the crop can be made even for the encoder (animations are scaled anyway)
+      if options.auto_reframe.is_none() && options.animation.is_none() && !is_detected {
+        let policy = options.dimension_policy.unwrap_or_default();
+        match &options.keyframes {
+          Some(keyframes) => {
+            apply_dimension_policy_to_keyframes(&dimensions.size, keyframes, policy, video.get_display_size())?;
+          }
+          None => {
+            apply_dimension_policy(&dimensions, policy, video.get_display_size())?;
+          }
+        }
+      }
+
+      // 6) check the encoding options against the detected ffmpeg
+      if let Some(encoding) = &options.encoding {
+        get_encoding_args(encoding, &options.input_file_path)?;
+      }
+      if let Some(audio) = &options.audio {
+        get_audio_args(audio, options)?;
+      }
+      if let Some(animation) = &options.animation {
+        if options.encoding.is_some() || options.audio.is_some() {
+          return Err(String::from("Animations can't be combined with encoding or audio options."));
+        }
+        if options.keep_metadata.is_some() || options.keep_chapters.is_some() {
+          return Err(String::from("Animations don't have metadata or chapters."));
+        }
+        get_animation_args(animation)?;
+      } else {
+        // also checks the selected audio tracks
+        get_stream_args(options, &video, &get_container(options.encoding.as_ref(), &options.input_file_path))?;
       }
 
-      // 4) check if output size if larger than input size
-      if
-        options.dimensions.size.width > width - options.dimensions.top_left_point.x ||
-        options.dimensions.size.height > height - options.dimensions.top_left_point.y
-      {
-        return Err(String::from("The output size is larger than the input image size."));
+      // 7) check the thumbnails and HLS packaging against the cropped video
+      if let Some(thumbnails) = &options.thumbnails {
+        if options.animation.is_some() {
+          return Err(String::from("Thumbnails can't be created for animations."));
+        }
+        let start = options.start.unwrap_or(0.0);
+        let duration = options
+          .get_trim_duration()
+          .or_else(|| video.duration.map(|duration| duration - start));
+        // the size of videos with removed black bars is checked after cropping
+        let size = match options.auto_reframe {
+          Some(aspect) => Some(get_reframe_size(video.get_display_size(), aspect)),
+          None if !is_detected => Some(dimensions.size),
+          None => None,
+        };
+        check_thumbnail_options(thumbnails, duration, size.map(|size| (size.width, size.height)))?;
+      }
+      if let Some(hls) = &options.hls {
+        if options.animation.is_some() {
+          return Err(String::from("Animations can't be packaged for HLS."));
+        }
+        // the height of reframed videos and videos with removed black bars is checked after cropping
+        let height = if options.auto_reframe.is_none() && !is_detected {
+          Some(dimensions.size.height)
+        } else {
+          None
+        };
+        check_hls_options(hls, height)?;
       }
+      probed_video = Some(video);
+    }
+
+    // 8) check resize and format options
+    if let Some(size) = &options.resize {
+      if !is_image {
+        return Err(String::from("Resizing is only supported for images."));
+      }
+      if size.width == 0 || size.height == 0 {
+        return Err(String::from("The resize dimensions must be larger than 0."));
+      }
+    }
+    if let Some(format) = &options.format {
+      if !is_image {
+        return Err(String::from("Changing the output format is only supported for images."));
+      }
+      match find_format(format) {
+        Some(media_format) if media_format.media_type == MediaType::Image && media_format.can_encode => (),
+        _ => {
+          return Err(format!("The output format \"{}\" is not supported.", format));
+        }
+      }
+    }
+
+    // 9) check watermark options
+    if let Some(watermark) = &options.watermark {
+      if !is_image {
+        return Err(String::from("Watermarks are only supported for images."));
+      }
+      match &watermark.file_path {
+        Some(file_path) => {
+          if !Path::new(file_path).exists() {
+            return Err(String::from("The watermark file does not exist."));
+          }
+          if !is_image_file(file_path) {
+            return Err(String::from("The watermark file is not an image."));
+          }
+        }
+        None => {
+          return Err(String::from("The watermark file path is missing."));
+        }
+      }
+      if let Some(opacity) = watermark.opacity {
+        if !(0.0..=1.0).contains(&opacity) {
+          return Err(String::from("The watermark opacity must be between 0 and 1."));
+        }
+      }
+      if let Some(scale) = watermark.scale {
+        if scale <= 0.0 || scale > 1.0 {
+          return Err(String::from("The watermark scale must be between 0 (exclusive) and 1."));
+        }
+      }
+    }
+
+    Ok(probed_video)
+  }
+
+  /// Validates the parameters of a poster frame.
+  /// If the validation is successful, it returns `true`, or an error message otherwise.
+  pub fn validate_poster_options(options: &PosterParameters) -> Result<bool, String> {
+    // 0) check if input file exists and is a video
+    if !Path::new(&options.input_file_path).exists() {
+      return Err(String::from("The input file does not exist."));
+    }
+    if !is_video_file(&options.input_file_path) {
+      return Err(String::from("Poster frames can only be extracted from videos."));
+    }
+
+    // 1) check the output format and file
+    let format = options.format.as_deref().unwrap_or(DEFAULT_POSTER_FORMAT);
+    match find_format(format) {
+      Some(media_format) if media_format.media_type == MediaType::Image && media_format.can_encode => (),
+      _ => {
+        return Err(format!("The output format \"{}\" is not supported.", format));
+      }
+    }
+    if let Some(output_path) = &options.output_file_path {
+      if PathBuf::from(output_path).with_extension(format.to_lowercase()).exists() {
+        return Err(String::from("The output file already exists."));
+      }
+    }
+
+    // 2) check the time and the crop against the video
+    let video = probe_video(&options.input_file_path, &CancelToken::new())?;
+    if let Some(time) = options.time {
+      if !time.is_finite() || time < 0.0 {
+        return Err(String::from("The poster time must not be negative."));
+      }
+      if let Some(duration) = video.duration {
+        if time >= duration {
+          return Err(format!("The poster time ({}s) is after the end of the video ({}s).", time, duration));
+        }
+      }
+    }
+    if let Some(dimensions) = &options.dimensions {
+      check_crop_bounds(dimensions, video.get_display_size(), "video")?;
+    }
+
+    Ok(true)
+  }
+
+  /// Checks if the input of the black bar detection is an existing video.
+  pub fn validate_black_bar_input(input_file_path: &str) -> Result<bool, String> {
+    if !Path::new(input_file_path).exists() {
+      return Err(String::from("The input file does not exist."));
+    }
+    if !is_video_file(input_file_path) {
+      return Err(String::from("Black bars can only be detected in videos."));
     }
 
     Ok(true)
   }
+
+  /// Validates the scene detection options.
+  pub fn validate_scene_options(options: &SceneParameters) -> Result<bool, String> {
+    // 0) check if input file exists and is a video
+    if !Path::new(&options.input_file_path).exists() {
+      return Err(String::from("The input file does not exist."));
+    }
+    if !is_video_file(&options.input_file_path) {
+      return Err(String::from("Scene changes can only be detected in videos."));
+    }
+
+    // 1) check the options and the thumbnail of the first cut
+    check_scene_options(options)?;
+    if let Some(output_path) = &options.output_file_path {
+      if get_scene_thumbnail_path(&PathBuf::from(output_path).with_extension("jpg"), 1).exists() {
+        return Err(String::from("The output file already exists."));
+      }
+    }
+
+    // 2) check if the input is a readable video
+    probe_video(&options.input_file_path, &CancelToken::new())?;
+
+    Ok(true)
+  }
+
+  /// Checks if the crop rectangle fits into the input of the given size.
+  ///
+  /// ## Usage:
+  /// ```
+  /// use drag_and_crop::utils::validation::validation::check_crop_bounds;
+  /// use drag_and_crop::{ CropDimensions, ImageSize, Point };
+  ///
+  /// let dimensions = CropDimensions {
+  ///   top_left_point: Point { x: 100, y: 100 },
+  ///   size: ImageSize { width: 500, height: 400 },
+  /// };
+  /// assert_eq!(check_crop_bounds(&dimensions, (1920, 1080), "video"), Ok(()));
+  /// assert_eq!(
+  ///   check_crop_bounds(&dimensions, (480, 640), "video"),
+  ///   Err(String::from("The output size is larger than the input video size."))
+  /// );
+  /// ```
+  pub fn check_crop_bounds(
+    dimensions: &CropDimensions,
+    (width, height): (u32, u32),
+    media_type: &str
+  ) -> Result<(), String> {
+    if dimensions.top_left_point.x >= width || dimensions.top_left_point.y >= height {
+      return Err(String::from("The top left point is out of bounds."));
+    }
+    if
+      dimensions.size.width > width - dimensions.top_left_point.x ||
+      dimensions.size.height > height - dimensions.top_left_point.y
+    {
+      return Err(format!("The output size is larger than the input {} size.", media_type));
+    }
+
+    Ok(())
+  }
+
+  /// Checks if the crop keyframes are sorted and if the crop stays within the frame at every keyframe.
+  pub fn check_keyframes(
+    keyframes: &[CropKeyframe],
+    size: &ImageSize,
+    frame_size: (u32, u32),
+    video_duration: Option<f64>
+  ) -> Result<(), String> {
+    if keyframes.is_empty() {
+      return Err(String::from("At least one crop keyframe is required."));
+    }
+    let mut previous_time = None;
+    for keyframe in keyframes {
+      if !keyframe.time.is_finite() || keyframe.time < 0.0 {
+        return Err(String::from("The crop keyframe times must not be negative."));
+      }
+      if previous_time.is_some_and(|previous_time| keyframe.time <= previous_time) {
+        return Err(String::from("The crop keyframes must be sorted by time."));
+      }
+      if video_duration.is_some_and(|duration| keyframe.time > duration) {
+        return Err(format!("The crop keyframe at {}s is after the end of the video.", keyframe.time));
+      }
+      let dimensions = CropDimensions {
+        top_left_point: keyframe.top_left_point,
+        size: ImageSize { width: size.width, height: size.height },
+      };
+      if let Err(error) = check_crop_bounds(&dimensions, frame_size, "video") {
+        return Err(format!("{} (keyframe at {}s).", error.trim_end_matches('.'), keyframe.time));
+      }
+      previous_time = Some(keyframe.time);
+    }
+
+    Ok(())
+  }
+
+  /// Checks if the trimmed range (`start`, `end` and `duration`) is within the video duration.
+  /// The range is only checked against the duration if it's known.
+  ///
+  /// ## Usage:
+  /// ```
+  /// use drag_and_crop::utils::validation::validation::check_time_range;
+  /// use drag_and_crop::CropParameters;
+  ///
+  /// let params = CropParameters { start: Some(5.0), end: Some(15.0), ..Default::default() };
+  /// assert_eq!(check_time_range(&params, Some(20.0)), Ok(()));
+  /// assert_eq!(
+  ///   check_time_range(&params, Some(10.0)),
+  ///   Err(String::from("The end time (15s) is after the end of the video (10s)."))
+  /// );
+  ///
+  /// let params = CropParameters { start: Some(5.0), end: Some(5.0), ..Default::default() };
+  /// assert_eq!(
+  ///   check_time_range(&params, None),
+  ///   Err(String::from("The end time must be after the start time."))
+  /// );
+  /// ```
+  pub fn check_time_range(options: &CropParameters, video_duration: Option<f64>) -> Result<(), String> {
+    let start = options.start.unwrap_or(0.0);
+    if !start.is_finite() || start < 0.0 {
+      return Err(String::from("The start time must not be negative."));
+    }
+    if options.end.is_some() && options.duration.is_some() {
+      return Err(String::from("The end time and duration can't be set at the same time."));
+    }
+    if let Some(end) = options.end {
+      if !end.is_finite() || end <= start {
+        return Err(String::from("The end time must be after the start time."));
+      }
+    }
+    if let Some(duration) = options.duration {
+      if !duration.is_finite() || duration <= 0.0 {
+        return Err(String::from("The duration must be larger than 0."));
+      }
+    }
+
+    if let Some(video_duration) = video_duration {
+      if start >= video_duration {
+        return Err(
+          format!("The start time ({}s) is after the end of the video ({}s).", start, video_duration)
+        );
+      }
+      let end = start + options.get_trim_duration().unwrap_or(0.0);
+      if end > video_duration {
+        return Err(
+          format!("The end time ({}s) is after the end of the video ({}s).", end, video_duration)
+        );
+      }
+    }
+
+    Ok(())
+  }
 }
@@ -1,28 +1,115 @@
 pub mod routes {
-  use drag_and_crop::{ CropRequest, UploadRequest };
-  use crate::web::handlers::handlers::{ handle_crop_request, handle_upload_request };
+  use drag_and_crop::{ ApiResponse, BlackBarRequest, CropRequest, PosterRequest, SceneRequest, UploadRequest };
+  use drag_and_crop::utils::cancel::cancel::CancelToken;
+  use drag_and_crop::utils::progress::progress::VideoProgress;
+  use tokio::{ sync::mpsc::unbounded_channel, task::{ JoinError, JoinHandle } };
+  use crate::web::handlers::handlers::{
+    handle_black_bar_request,
+    handle_crop_request,
+    handle_health_request,
+    handle_poster_request,
+    handle_scene_request,
+    handle_upload_request,
+  };
+  use crate::web::jobs::jobs::{ JobGuard, VideoJobs };
   use rocket::{
-    serde::json::{ Json, Value },
+    serde::json::{ json, Json, Value },
     response::{ status, stream::{ Event, EventStream } },
     http::Status,
+    State,
   };
 
+  #[get("/health")]
+  pub fn get_health() -> Value {
+    handle_health_request()
+  }
   #[post("/crop-image", format = "json", data = "<options>")]
   pub async fn post_crop_image(options: Json<CropRequest>) -> Value {
-    handle_crop_request(options, true).await
+    handle_crop_request(options, true, None, CancelToken::new()).await
   }
+  /// Streams a "job" event with the job ID, "progress" events with the ffmpeg progress
+  /// and a final (unnamed) event with the result.
+  /// The job is cancelled if the client disconnects before receiving the result.
   #[post("/crop-video", format = "json", data = "<options>")]
-  pub async fn post_crop_video(options: Json<CropRequest>) -> EventStream![] {
+  pub async fn post_crop_video(
+    options: Json<CropRequest>,
+    jobs: &State<VideoJobs>
+  ) -> EventStream![] {
+    let guard = jobs.start();
+    let (sender, mut receiver) = unbounded_channel();
+    let job = tokio::spawn(handle_crop_request(options, false, Some(sender), guard.token.clone()));
     EventStream! {
-      yield Event::data("Processing...");
-      let result = handle_crop_request(options, false).await;
-      yield Event::data(result.to_string());
+      // the guard lives as long as the stream, dropping the stream cancels the job
+      let guard = guard;
+      yield Event::json(&json!({ "job_id": guard.id })).event("job");
+      yield Event::json(&VideoProgress::default()).event("progress");
+      while let Some(progress) = receiver.recv().await {
+        yield Event::json(&progress).event("progress");
+      }
+      yield Event::data(get_job_result(job.await).to_string());
     }
   }
+  #[post("/crop-video/<job_id>/cancel")]
+  pub fn post_cancel_video_job(job_id: &str, jobs: &State<VideoJobs>) -> Value {
+    if jobs.cancel(job_id) {
+      json!(ApiResponse::<String> {
+        success: true,
+        message: None,
+        data: None,
+      })
+    } else {
+      json!(ApiResponse::<String> {
+        success: false,
+        message: Some(String::from("The job does not exist or has already finished.")),
+        data: None,
+      })
+    }
+  }
+  /// Streams a "job" event with the job ID and a final (unnamed) event with the result.
+  #[post("/extract-poster", format = "json", data = "<options>")]
+  pub async fn post_extract_poster(options: Json<PosterRequest>, jobs: &State<VideoJobs>) -> EventStream![] {
+    let guard = jobs.start();
+    let job = tokio::spawn(handle_poster_request(options, guard.token.clone()));
+    stream_job_result(guard, job)
+  }
+  /// Streams a "job" event with the job ID and a final (unnamed) event with the result.
+  #[post("/detect-scenes", format = "json", data = "<options>")]
+  pub async fn post_detect_scenes(options: Json<SceneRequest>, jobs: &State<VideoJobs>) -> EventStream![] {
+    let guard = jobs.start();
+    let job = tokio::spawn(handle_scene_request(options, guard.token.clone()));
+    stream_job_result(guard, job)
+  }
+  /// Streams a "job" event with the job ID and a final (unnamed) event with the result.
+  #[post("/detect-black-bars", format = "json", data = "<options>")]
+  pub async fn post_detect_black_bars(options: Json<BlackBarRequest>, jobs: &State<VideoJobs>) -> EventStream![] {
+    let guard = jobs.start();
+    let job = tokio::spawn(handle_black_bar_request(options, guard.token.clone()));
+    stream_job_result(guard, job)
+  }
   #[post("/upload-media", format = "json", data = "<options>")]
   pub async fn post_upload_media(options: Json<UploadRequest>) -> Value {
     handle_upload_request(options).await
   }
+  /// Streams the job ID and the result of a video job that doesn't report its progress.
+  /// Rocket runs the handlers detached, so only dropping the stream (when the client disconnects)
+  /// drops the guard before the job has finished, which cancels the job.
+  fn stream_job_result(guard: JobGuard, job: JoinHandle<Value>) -> EventStream![] {
+    EventStream! {
+      let guard = guard;
+      yield Event::json(&json!({ "job_id": guard.id })).event("job");
+      yield Event::data(get_job_result(job.await).to_string());
+    }
+  }
+  fn get_job_result(result: Result<Value, JoinError>) -> Value {
+    match result {
+      Ok(result) => result,
+      Err(_) => json!(ApiResponse::<String> {
+        success: false,
+        message: Some(String::from("The video processing was interrupted.")),
+        data: None,
+      }),
+    }
+  }
   #[options("/crop-image")]
   pub fn options_crop_image() -> status::Custom<String> {
     status::Custom(Status::NoContent, String::new())
@@ -31,6 +118,22 @@ pub mod routes {
   pub fn options_crop_video() -> status::Custom<String> {
     status::Custom(Status::NoContent, String::new())
   }
+  #[options("/crop-video/<_job_id>/cancel")]
+  pub fn options_cancel_video_job(_job_id: &str) -> status::Custom<String> {
+    status::Custom(Status::NoContent, String::new())
+  }
+  #[options("/extract-poster")]
+  pub fn options_extract_poster() -> status::Custom<String> {
+    status::Custom(Status::NoContent, String::new())
+  }
+  #[options("/detect-scenes")]
+  pub fn options_detect_scenes() -> status::Custom<String> {
+    status::Custom(Status::NoContent, String::new())
+  }
+  #[options("/detect-black-bars")]
+  pub fn options_detect_black_bars() -> status::Custom<String> {
+    status::Custom(Status::NoContent, String::new())
+  }
   #[options("/upload-media")]
   pub fn options_upload_media() -> status::Custom<String> {
     status::Custom(Status::NoContent, String::new())
@@ -1,5 +1,6 @@
 use std::{ path::Path, fs, error::Error };
 use drag_and_crop::{ utils::file::file::format_file_name_for_storage, UploadResponse };
+use drag_and_crop::utils::format::format::find_format_by_path;
 use yup_oauth2::{ ServiceAccountAuthenticator, read_service_account_key };
 use rocket::serde::json::serde_json;
 use urlencoding::encode;
@@ -69,12 +70,35 @@ pub async fn upload_file(
   file_path: &str,
   is_image: bool,
   is_raw_file: bool
+) -> Result<String, Box<dyn Error + Send + Sync>> {
+  let file_name = format_file_name_for_storage(&file_path, !is_raw_file);
+  let folder_name = if is_image { "images" } else { "videos" };
+  let storage_directory = if is_raw_file { "raw" } else { "cropped" };
+  let storage_file_name = format!("{}/{}/{}", storage_directory, &folder_name, &file_name);
+
+  upload_file_to(access_token, file_path, &storage_file_name, is_image).await
+}
+
+/**
+ * Uploads file to the given path within the storage bucket and returns the resource path
+ */
+pub async fn upload_file_to(
+  access_token: &str,
+  file_path: &str,
+  storage_file_name: &str,
+  is_image: bool
 ) -> Result<String, Box<dyn Error + Send + Sync>> {
   let client = reqwest::Client::builder().build()?;
 
   // headers
   let mut headers = reqwest::header::HeaderMap::new();
-  let file_type = if is_image { "image/jpeg" } else { "video/mp4" };
+  let file_type = match find_format_by_path(file_path) {
+    Some(format) => format.mime_type,
+    None if file_path.ends_with(".vtt") => "text/vtt",
+    None if file_path.ends_with(".m3u8") => "application/vnd.apple.mpegurl",
+    None if file_path.ends_with(".ts") => "video/mp2t",
+    None => if is_image { "image/jpeg" } else { "video/mp4" },
+  };
   headers.insert("Content-Type", file_type.parse()?);
   headers.insert("Authorization", format!("Bearer {}", access_token).parse()?);
 
@@ -82,13 +106,9 @@ pub async fn upload_file(
   let bytes = std::fs::read(file_path)?;
 
   // query parameters
-  let file_name = format_file_name_for_storage(&file_path, !is_raw_file);
-  let folder_name = if is_image { "images" } else { "videos" };
-  let storage_directory = if is_raw_file { "raw" } else { "cropped" };
-  let storage_file_name = format!("{}/{}/{}", storage_directory, &folder_name, &file_name);
   let query: [(&str, &str); 2] = [
     ("uploadType", "media"),
-    ("name", &storage_file_name),
+    ("name", storage_file_name),
   ];
 
   // request
@@ -1,12 +1,33 @@
 pub mod handlers {
-  use std::path::Path;
-  use drag_and_crop::{ CropRequest, crop_video, UploadRequest };
-  use drag_and_crop::{ crop_image, ApiResponse, CropParameters };
+  use std::{ error::Error, fs, path::Path };
+  use drag_and_crop::{ CropRequest, crop_video_with_progress, extract_poster, HlsFiles, PosterParameters, PosterRequest, UploadRequest };
+  use drag_and_crop::{ detect_black_bars, detect_scenes, BlackBarRequest, SceneParameters, SceneRequest };
+  use drag_and_crop::utils::cancel::cancel::CancelToken;
+  use drag_and_crop::utils::progress::progress::VideoProgress;
+  use tokio::sync::mpsc::UnboundedSender;
+  use drag_and_crop::{ crop_image, ApiResponse, CropParameters, FfmpegCapabilities, HealthResponse, ThumbnailFiles, VideoCropResponse };
+  use drag_and_crop::utils::ffmpeg::ffmpeg::get_ffmpeg;
+  use drag_and_crop::utils::file::file::{ format_file_name_for_storage, is_image_file };
+  use drag_and_crop::utils::thumbnails::thumbnails::get_sprite_path;
   use rocket::serde::json::{ json, Json, Value };
-  use crate::web::firebase::{ download_file, get_access_token, upload_file };
+  use crate::web::firebase::{ download_file, get_access_token, upload_file, upload_file_to };
   use crate::web::url::url::{ download_from_url, is_image_url, is_video_url };
 
-  pub async fn handle_crop_request(options: Json<CropRequest>, is_image: bool) -> Value {
+  /// Folder with the watermarks that can be referenced by a local file path.
+  const WATERMARK_DIRECTORY: &str = "./static/watermarks";
+
+  pub async fn handle_crop_request(
+    options: Json<CropRequest>,
+    is_image: bool,
+    progress: Option<UnboundedSender<VideoProgress>>,
+    cancel: CancelToken
+  ) -> Value {
+    if !is_image {
+      if let Err(response) = check_video_processing() {
+        return response;
+      }
+    }
+
     // 1) get access token
     let token_result = get_access_token().await;
     if token_result.is_err() {
@@ -20,59 +41,107 @@ pub mod handlers {
     let media_type = if is_image { "image" } else { "video" };
 
     // 2) download file from URL or firebase storage
-    let mut file_name = String::new();
-    if let Some(storage_path) = &options.storage_file_path {
-      let download_result = download_file(token, &storage_path).await;
-      if download_result.is_err() {
-        return json!(ApiResponse::<String> {
-          success: false,
-          message: Some(format!("There was an error with the {} url.", media_type)),
-          data: None,
-        });
+    let file_name = match download_input(token, &options.storage_file_path, &options.url, media_type).await {
+      Ok(file_name) => file_name,
+      Err(response) => {
+        return response;
       }
-      file_name = download_result.unwrap();
-    } else if let Some(url) = &options.url {
-      let download_result = download_from_url(url).await;
-      if download_result.is_none() {
-        return json!(ApiResponse::<String> {
-          success: false,
-          message: Some(format!("There was an error with the {} url.", media_type)),
-          data: None,
-        });
-      }
-      file_name = download_result.unwrap();
-    }
+    };
 
-    if file_name == "" {
-      return json!(ApiResponse::<String> {
-        success: false,
-        message: Some(format!("Bad request - both storage file path and URL fields are empty.")),
-        data: None,
-      });
+    // 3) resolve watermark file
+    let mut request = options.into_inner();
+    if let Some(watermark) = &mut request.watermark {
+      if let Some(storage_path) = &watermark.storage_file_path {
+        let download_result = download_file(token, storage_path).await;
+        if download_result.is_err() {
+          return json!(ApiResponse::<String> {
+            success: false,
+            message: Some(String::from("There was an error with the watermark storage path.")),
+            data: None,
+          });
+        }
+        watermark.file_path = Some(download_result.unwrap());
+      } else if let Some(file_path) = &watermark.file_path {
+        // local watermarks can only be picked from the watermarks folder
+        let only_watermark_name = Path::new(file_path).file_name().unwrap_or_default();
+        let local_path = Path::new(WATERMARK_DIRECTORY).join(only_watermark_name);
+        watermark.file_path = Some(local_path.to_string_lossy().to_string());
+      }
     }
 
-    // 3) prepare cropping parameteres
+    // 4) prepare cropping parameteres
     let only_file_name = Path::new(&file_name).file_name().unwrap().to_str().unwrap();
     let cropped_file_name = format!("./tmp/cropped-{}", only_file_name);
+    if let Some(thumbnails) = &mut request.thumbnails {
+      // the sprite is referenced by its path in the storage bucket, next to the cropped video
+      if thumbnails.sprite_url.is_none() {
+        let sprite_path = get_sprite_path(Path::new(&cropped_file_name));
+        let sprite_name = format_file_name_for_storage(&sprite_path.to_string_lossy(), true);
+        thumbnails.sprite_url = Some(format!("cropped/videos/{}", sprite_name));
+      }
+    }
+    let input_file_name = file_name.clone();
     let options = CropParameters {
       input_file_path: file_name,
       output_file_path: Some(cropped_file_name),
-      dimensions: options.into_inner().dimensions,
+      dimensions: request.dimensions,
+      watermark: request.watermark,
+      resize: request.resize,
+      format: request.format,
+      limits: None,
+      start: request.start,
+      end: request.end,
+      duration: request.duration,
+      trim_mode: request.trim_mode,
+      encoding: request.encoding,
+      audio: request.audio,
+      keyframes: request.keyframes,
+      auto_reframe: request.auto_reframe,
+      animation: request.animation,
+      thumbnails: request.thumbnails,
+      hls: request.hls,
+      dimension_policy: request.dimension_policy,
+      keep_metadata: request.keep_metadata,
+      keep_chapters: request.keep_chapters,
+      remove_black_bars: request.remove_black_bars,
     };
 
-    // 4) crop image/video
-    let result = if is_image { crop_image(&options) } else { crop_video(&options) };
+    // 5) crop image/video (on a blocking thread, ffmpeg can take a while)
+    let job_token = cancel.clone();
+    let result = tokio::task
+      ::spawn_blocking(move || {
+        if is_image {
+          crop_image(&options)
+        } else {
+          crop_video_with_progress(&options, &job_token, |video_progress| {
+            if let Some(sender) = &progress {
+              let _ = sender.send(video_progress.clone());
+            }
+          })
+        }
+      }).await
+      .unwrap_or_else(|error| Err(format!("The {} processing was interrupted: {}", media_type, error)));
     if let Err(crop_error) = result {
+      if cancel.is_cancelled() {
+        // the job was cancelled or ran out of time, the partial output was already deleted while cropping
+        let _ = fs::remove_file(&input_file_name);
+      }
       return json!(ApiResponse::<String> {
         success: false,
         message: Some(crop_error),
         data: None,
       });
     }
-    let cropped_file_path = result.unwrap();
+    let crop_result = result.unwrap();
 
-    // 5) upload result to Firebase storage
-    let upload_result = upload_file(token, cropped_file_path.as_str(), is_image, false).await;
+    // 6) upload result to Firebase storage
+    // animations cropped from videos are stored with the images
+    // HLS packages are stored under one prefix, the master playlist is returned instead of the video
+    let is_image_result = is_image || is_image_file(&crop_result.file_path);
+    let upload_result = match &crop_result.hls {
+      Some(hls) => upload_hls(token, hls).await,
+      None => upload_file(token, crop_result.file_path.as_str(), is_image_result, false).await,
+    };
     if upload_result.is_err() {
       return json!(ApiResponse::<String> {
         success: false,
@@ -82,14 +151,269 @@ pub mod handlers {
     }
     let cropped_file_url = upload_result.unwrap();
 
+    // 7) upload the thumbnails next to the cropped video
+    let mut thumbnail_files = None;
+    if let Some(thumbnails) = &crop_result.thumbnails {
+      let sprite_result = upload_file(token, &thumbnails.sprite_file_path, false, false).await;
+      let vtt_result = upload_file(token, &thumbnails.vtt_file_path, false, false).await;
+      match (sprite_result, vtt_result) {
+        (Ok(sprite_file_path), Ok(vtt_file_path)) => {
+          thumbnail_files = Some(ThumbnailFiles { sprite_file_path, vtt_file_path });
+        }
+        _ => {
+          return json!(ApiResponse::<String> {
+            success: false,
+            message: Some(String::from("There was an error while uploading the video thumbnails.")),
+            data: None,
+          });
+        }
+      }
+    }
+
+    // 8) return result
+    let warnings = crop_result.warnings;
+    let message = if warnings.is_empty() { None } else { Some(warnings.join(" ")) };
+    if is_image {
+      return json!(ApiResponse {
+        success: true,
+        message,
+        data: Some(cropped_file_url),
+      });
+    }
+    json!(ApiResponse {
+      success: true,
+      message,
+      data: Some(VideoCropResponse {
+        storage_file_path: cropped_file_url,
+        start: crop_result.start,
+        end: crop_result.end,
+        size: crop_result.size,
+        thumbnails: thumbnail_files,
+      }),
+    })
+  }
+
+  pub async fn handle_poster_request(options: Json<PosterRequest>, cancel: CancelToken) -> Value {
+    if let Err(response) = check_video_processing() {
+      return response;
+    }
+
+    // 1) get access token
+    let token_result = get_access_token().await;
+    if token_result.is_err() {
+      return json!(ApiResponse::<String> {
+        success: false,
+        message: Some(String::from("Failed to authenticate the request.")),
+        data: None,
+      });
+    }
+    let token = token_result.as_ref().unwrap().as_str();
+
+    // 2) download video from URL or firebase storage
+    let file_name = match download_input(token, &options.storage_file_path, &options.url, "video").await {
+      Ok(file_name) => file_name,
+      Err(response) => {
+        return response;
+      }
+    };
+
+    // 3) prepare poster parameters
+    let request = options.into_inner();
+    let only_file_name = Path::new(&file_name).file_name().unwrap().to_str().unwrap();
+    let poster_file_name = format!("./tmp/cropped-{}", only_file_name);
+    let input_file_name = file_name.clone();
+    let options = PosterParameters {
+      input_file_path: file_name,
+      output_file_path: Some(poster_file_name),
+      time: request.time,
+      dimensions: request.dimensions,
+      format: request.format,
+    };
+
+    // 4) extract the frame (on a blocking thread, finding the sharpest frame decodes the whole video)
+    let job_token = cancel.clone();
+    let result = tokio::task
+      ::spawn_blocking(move || extract_poster(&options, &job_token)).await
+      .unwrap_or_else(|error| Err(format!("The video processing was interrupted: {}", error)));
+    let poster_result = match result {
+      Ok(poster_result) => poster_result,
+      Err(error) => {
+        if cancel.is_cancelled() {
+          // the job was cancelled or ran out of time, the frames were already deleted while extracting the poster
+          let _ = fs::remove_file(&input_file_name);
+        }
+        return json!(ApiResponse::<String> {
+          success: false,
+          message: Some(error),
+          data: None,
+        });
+      }
+    };
+
+    // 5) upload the poster to Firebase storage, next to the cropped images
+    let upload_result = upload_file(token, poster_result.file_path.as_str(), true, false).await;
+    if upload_result.is_err() {
+      return json!(ApiResponse::<String> {
+        success: false,
+        message: Some(String::from("There was an error while getting the poster URL.")),
+        data: None,
+      });
+    }
+    let poster_url = upload_result.unwrap();
+
     // 6) return result
+    let mut messages = poster_result.warnings;
+    if let Some(time) = poster_result.start {
+      messages.push(format!("The frame at {:.3}s was extracted.", time));
+    }
     json!(ApiResponse {
       success: true,
-      message: None,
-      data: Some(cropped_file_url),
+      message: Some(messages.join(" ")),
+      data: Some(poster_url),
+    })
+  }
+
+  pub async fn handle_scene_request(options: Json<SceneRequest>, cancel: CancelToken) -> Value {
+    if let Err(response) = check_video_processing() {
+      return response;
+    }
+
+    // 1) get access token
+    let token_result = get_access_token().await;
+    if token_result.is_err() {
+      return json!(ApiResponse::<String> {
+        success: false,
+        message: Some(String::from("Failed to authenticate the request.")),
+        data: None,
+      });
+    }
+    let token = token_result.as_ref().unwrap().as_str();
+
+    // 2) download video from URL or firebase storage
+    let file_name = match download_input(token, &options.storage_file_path, &options.url, "video").await {
+      Ok(file_name) => file_name,
+      Err(response) => {
+        return response;
+      }
+    };
+
+    // 3) prepare scene parameters, the thumbnails are named like the cropped files
+    let request = options.into_inner();
+    let only_file_name = Path::new(&file_name).file_name().unwrap().to_str().unwrap();
+    let scenes_file_name = format!("./tmp/cropped-{}", only_file_name);
+    let input_file_name = file_name.clone();
+    let options = SceneParameters {
+      input_file_path: file_name,
+      output_file_path: Some(scenes_file_name),
+      threshold: request.threshold,
+      thumbnail_width: request.thumbnail_width,
+      max_scenes: request.max_scenes,
+    };
+
+    // 4) detect the scenes (on a blocking thread, the whole video is decoded)
+    let job_token = cancel.clone();
+    let result = tokio::task
+      ::spawn_blocking(move || detect_scenes(&options, &job_token)).await
+      .unwrap_or_else(|error| Err(format!("The video processing was interrupted: {}", error)));
+    let mut scenes = match result {
+      Ok(scenes) => scenes,
+      Err(error) => {
+        if cancel.is_cancelled() {
+          // the job was cancelled or ran out of time, the thumbnails were already deleted while detecting the scenes
+          let _ = fs::remove_file(&input_file_name);
+        }
+        return json!(ApiResponse::<String> {
+          success: false,
+          message: Some(error),
+          data: None,
+        });
+      }
+    };
+
+    // 5) upload the thumbnails to Firebase storage, next to the cropped images
+    for scene in &mut scenes {
+      match upload_file(token, &scene.thumbnail, true, false).await {
+        Ok(storage_path) => {
+          scene.thumbnail = storage_path;
+        }
+        Err(_) => {
+          return json!(ApiResponse::<String> {
+            success: false,
+            message: Some(String::from("There was an error while uploading the scene thumbnails.")),
+            data: None,
+          });
+        }
+      }
+    }
+
+    // 6) return result
+    json!(ApiResponse {
+      success: true,
+      message: Some(format!("{} scene changes were detected.", scenes.len())),
+      data: Some(scenes),
     })
   }
 
+  pub async fn handle_black_bar_request(options: Json<BlackBarRequest>, cancel: CancelToken) -> Value {
+    if let Err(response) = check_video_processing() {
+      return response;
+    }
+
+    // 1) get access token
+    let token_result = get_access_token().await;
+    if token_result.is_err() {
+      return json!(ApiResponse::<String> {
+        success: false,
+        message: Some(String::from("Failed to authenticate the request.")),
+        data: None,
+      });
+    }
+    let token = token_result.as_ref().unwrap().as_str();
+
+    // 2) download video from URL or firebase storage
+    let file_name = match download_input(token, &options.storage_file_path, &options.url, "video").await {
+      Ok(file_name) => file_name,
+      Err(response) => {
+        return response;
+      }
+    };
+
+    // 3) detect the black bars (on a blocking thread, the video is sampled)
+    let input_file_name = file_name.clone();
+    let job_token = cancel.clone();
+    let result = tokio::task
+      ::spawn_blocking(move || detect_black_bars(&file_name, &job_token)).await
+      .unwrap_or_else(|error| Err(format!("The video processing was interrupted: {}", error)));
+    if cancel.is_cancelled() {
+      // the job was cancelled or ran out of time, the samples were already deleted while detecting the black bars
+      let _ = fs::remove_file(&input_file_name);
+    }
+
+    // 4) return result
+    match result {
+      Ok(dimensions) =>
+        json!(ApiResponse {
+          success: true,
+          message: Some(
+            format!(
+              "The video content is {}x{}px at ({}, {}).",
+              dimensions.size.width,
+              dimensions.size.height,
+              dimensions.top_left_point.x,
+              dimensions.top_left_point.y
+            )
+          ),
+          data: Some(dimensions),
+        }),
+      Err(error) =>
+        json!(ApiResponse::<String> {
+          success: false,
+          message: Some(error),
+          data: None,
+        }),
+    }
+  }
+
   pub async fn handle_upload_request(options: Json<UploadRequest>) -> Value {
     // 1) get access token
     let token_result = get_access_token().await;
@@ -143,4 +467,104 @@ pub mod handlers {
       data: Some(storage_path),
     })
   }
+
+  /// Downloads the input file from firebase storage or the URL and returns its local path,
+  /// or the error response.
+  async fn download_input(
+    token: &str,
+    storage_file_path: &Option<String>,
+    url: &Option<String>,
+    media_type: &str
+  ) -> Result<String, Value> {
+    let mut file_name = String::new();
+    if let Some(storage_path) = storage_file_path {
+      let download_result = download_file(token, storage_path).await;
+      if download_result.is_err() {
+        return Err(json!(ApiResponse::<String> {
+          success: false,
+          message: Some(format!("There was an error with the {} url.", media_type)),
+          data: None,
+        }));
+      }
+      file_name = download_result.unwrap();
+    } else if let Some(url) = url {
+      let download_result = download_from_url(url).await;
+      if download_result.is_none() {
+        return Err(json!(ApiResponse::<String> {
+          success: false,
+          message: Some(format!("There was an error with the {} url.", media_type)),
+          data: None,
+        }));
+      }
+      file_name = download_result.unwrap();
+    }
+
+    if file_name == "" {
+      return Err(json!(ApiResponse::<String> {
+        success: false,
+        message: Some(format!("Bad request - both storage file path and URL fields are empty.")),
+        data: None,
+      }));
+    }
+
+    Ok(file_name)
+  }
+
+  /// Uploads all files of the HLS package under the prefix of its folder and returns the master playlist path.
+  async fn upload_hls(token: &str, hls: &HlsFiles) -> Result<String, Box<dyn Error + Send + Sync>> {
+    let directory = Path::new(&hls.master_playlist_path).parent().unwrap_or(Path::new(""));
+    let prefix = format!("cropped/videos/{}", format_file_name_for_storage(&directory.to_string_lossy(), true));
+    let mut master_playlist_path = String::new();
+    for file_path in &hls.file_paths {
+      let file_name = Path::new(file_path).file_name().unwrap_or_default().to_string_lossy();
+      let storage_path = upload_file_to(token, file_path, &format!("{}/{}", prefix, file_name), false).await?;
+      if *file_path == hls.master_playlist_path {
+        master_playlist_path = storage_path;
+      }
+    }
+
+    Ok(master_playlist_path)
+  }
+
+  /// Images can still be cropped without ffmpeg (if it's optional), so only video processing reports the detection error.
+  /// The paths of the binaries are not exposed.
+  pub fn handle_health_request() -> Value {
+    match get_ffmpeg() {
+      Ok(ffmpeg) =>
+        json!(ApiResponse {
+          success: true,
+          message: None,
+          data: Some(HealthResponse {
+            ffmpeg: Some(FfmpegCapabilities {
+              version: ffmpeg.version.clone(),
+              ffprobe_version: ffmpeg.ffprobe_version.clone(),
+              video_encoders: ffmpeg.video_encoders.clone(),
+              audio_encoders: ffmpeg.audio_encoders.clone(),
+              filters: ffmpeg.filters.clone(),
+            }),
+          }),
+        }),
+      Err(error) =>
+        json!(ApiResponse {
+          success: true,
+          message: Some(format!("Video processing is unavailable. {}", error)),
+          data: Some(HealthResponse { ffmpeg: None }),
+        }),
+    }
+  }
+
+  /// Rejects video requests before anything is downloaded if ffmpeg wasn't found at startup.
+  fn check_video_processing() -> Result<(), Value> {
+    match get_ffmpeg() {
+      Ok(_) => Ok(()),
+      Err(error) =>
+        Err(
+          json!(ApiResponse::<String> {
+            success: false,
+            message: Some(format!("Video processing is unavailable. {}", error)),
+            data: None,
+          })
+        ),
+    }
+  }
 }
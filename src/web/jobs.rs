@@ -0,0 +1,47 @@
+pub mod jobs {
+  use std::{ collections::HashMap, sync::{ Arc, Mutex } };
+  use drag_and_crop::utils::cancel::cancel::CancelToken;
+  use uuid::Uuid;
+
+  /// Video jobs that are currently running, by their ID.
+  #[derive(Clone, Default)]
+  pub struct VideoJobs {
+    jobs: Arc<Mutex<HashMap<String, CancelToken>>>,
+  }
+
+  impl VideoJobs {
+    /// Registers a new job. The job is cancelled and unregistered when the returned guard is dropped.
+    pub fn start(&self) -> JobGuard {
+      let id = Uuid::new_v4().to_string();
+      let token = CancelToken::new();
+      self.jobs.lock().unwrap().insert(id.clone(), token.clone());
+      JobGuard { id, token, jobs: self.clone() }
+    }
+
+    /// Cancels the job and returns `false` if there is no such job.
+    pub fn cancel(&self, id: &str) -> bool {
+      match self.jobs.lock().unwrap().get(id) {
+        Some(token) => {
+          token.cancel();
+          true
+        }
+        None => false,
+      }
+    }
+  }
+
+  /// Keeps the job registered while the client is listening for its result.
+  pub struct JobGuard {
+    pub id: String,
+    pub token: CancelToken,
+    jobs: VideoJobs,
+  }
+
+  impl Drop for JobGuard {
+    fn drop(&mut self) {
+      // the client is gone (or has received the result), so there's no reason to keep ffmpeg running
+      self.token.cancel();
+      self.jobs.jobs.lock().unwrap().remove(&self.id);
+    }
+  }
+}
@@ -1,6 +1,7 @@
 pub mod url {
   use reqwest::Response;
   use regex::Regex;
+  use drag_and_crop::utils::format::format::{ get_extension_pattern, MediaType };
   use uuid::Uuid;
   use std::{ io::{ Cursor, copy }, path::Path, fs::File };
 
@@ -15,7 +16,11 @@ pub mod url {
   /// assert_eq!(result, true);
   /// ```
   pub fn is_image_url(url: &str) -> bool {
-    let regex = Regex::new(r"https:\/\/(.+?)(\.(png|jpg|jpeg|gif|bmp|webp|svg))(\?(.*))?").unwrap();
+    let pattern = format!(
+      r"https:\/\/(.+?)(\.({}))(\?(.*))?",
+      get_extension_pattern(Some(MediaType::Image))
+    );
+    let regex = Regex::new(&pattern).unwrap();
     if let Some(matches) = regex.captures(url) {
       return matches.get(2).is_some();
     }
@@ -34,7 +39,11 @@ pub mod url {
   /// assert_eq!(result, true);
   /// ```
   pub fn is_video_url(url: &str) -> bool {
-    let regex = Regex::new(r"https:\/\/(.+?)(\.(mp4|mkv|flv|avi|mov|wmv|webm))(\?(.*))?").unwrap();
+    let pattern = format!(
+      r"https:\/\/(.+?)(\.({}))(\?(.*))?",
+      get_extension_pattern(Some(MediaType::Video))
+    );
+    let regex = Regex::new(&pattern).unwrap();
     if let Some(matches) = regex.captures(url) {
       return matches.get(2).is_some();
     }
@@ -94,9 +103,8 @@ pub mod url {
     }
 
     let path_file_name_str = path_file_name.unwrap().to_str().unwrap();
-    let regex = Regex::new(
-      r"((.+?)(\.(png|jpg|jpeg|gif|bmp|webp|svg|mp4|mkv|flv|avi|mov|wmv|webm)))(\?(.*))?"
-    ).unwrap();
+    let pattern = format!(r"((.+?)(\.({})))(\?(.*))?", get_extension_pattern(None));
+    let regex = Regex::new(&pattern).unwrap();
     let matches = regex.captures(path_file_name_str);
     if matches.is_none() {
       return None;
@@ -1,5 +1,5 @@
 use std::time::SystemTime;
-use image::{ DynamicImage, ImageBuffer, Rgba };
+use image::{ DynamicImage, ImageBuffer, Rgb, Rgba };
 
 fn create_random_bytes(size: u32) -> Vec<u8> {
   let mut array = Vec::with_capacity(size as usize);
@@ -44,8 +44,17 @@ fn create_dummy_image(width: u32, height: u32) -> DynamicImage {
 #[cfg(test)]
 pub mod tests {
   use std::fs;
-  use drag_and_crop::{ crop_image, CropParameters, CropDimensions, ImageSize, Point };
-  use image::GenericImageView;
+  use drag_and_crop::{
+    crop_image,
+    CropParameters,
+    CropDimensions,
+    ImageSize,
+    Point,
+    Watermark,
+    Gravity,
+    utils::limits::limits::ImageLimits,
+  };
+  use image::{ ColorType, GenericImageView };
 
   use super::*;
 
@@ -66,15 +75,16 @@ pub mod tests {
     let params = CropParameters {
       input_file_path: String::from("./test.jpg"),
       output_file_path: Some(String::from(output_file_path)),
-      dimensions: CropDimensions {
+      dimensions: Some(CropDimensions {
         size: ImageSize { width: WANTED_WIDTH, height: WANTED_HEIGHT },
         top_left_point: Point { x: 50, y: 50 },
-      },
+      }),
+      ..Default::default()
     };
     let result = crop_image(&params).unwrap();
 
     // 3) test if result file matches
-    assert_eq!(result.as_str(), output_file_path);
+    assert_eq!(result.file_path.as_str(), output_file_path);
 
     // 4) test if resulting image dimensions match
     let metadata = image::open(output_file_path);
@@ -86,4 +96,233 @@ pub mod tests {
     let _ = fs::remove_file(input_file_path).unwrap();
     let _ = fs::remove_file(output_file_path).unwrap();
   }
+
+  #[test]
+  pub fn test_crop_image_with_watermark() {
+    // 1) prepare dummy image and a transparent watermark
+    let input_file_path = "./test-watermark-input.png";
+    let watermark_file_path = "./test-watermark-logo.png";
+    let output_file_path = "./test-watermark-cropped.png";
+    let _ = create_dummy_image(400, 300).save(input_file_path);
+    let logo = ImageBuffer::<Rgba<u8>, Vec<u8>>::from_pixel(50, 25, Rgba([255, 0, 0, 255]));
+    let _ = logo.save(watermark_file_path);
+
+    // 2) crop and apply the watermark to the bottom right corner
+    let params = CropParameters {
+      input_file_path: String::from(input_file_path),
+      output_file_path: Some(String::from(output_file_path)),
+      dimensions: Some(CropDimensions {
+        size: ImageSize { width: 200, height: 100 },
+        top_left_point: Point { x: 10, y: 10 },
+      }),
+      watermark: Some(Watermark {
+        file_path: Some(String::from(watermark_file_path)),
+        gravity: Some(Gravity::BottomRight),
+        opacity: Some(0.5),
+        scale: Some(0.25),
+        ..Default::default()
+      }),
+      ..Default::default()
+    };
+    crop_image(&params).unwrap();
+
+    // 3) test if the watermark was scaled and blended into the corner
+    let result = image::open(output_file_path).unwrap().to_rgba8();
+    assert_eq!(result.dimensions(), (200, 100));
+    let blended = result.get_pixel(199, 99);
+    assert!(blended[0] >= 127);
+    let untouched = result.get_pixel(199, 99 - 25);
+    let source = image::open(input_file_path).unwrap().to_rgba8();
+    assert_eq!(untouched, source.get_pixel(10 + 199, 10 + 99 - 25));
+
+    // 4) cleanup
+    fs::remove_file(input_file_path).unwrap();
+    fs::remove_file(watermark_file_path).unwrap();
+    fs::remove_file(output_file_path).unwrap();
+  }
+
+  #[test]
+  pub fn test_crop_image_keeps_bit_depth() {
+    // 1) prepare a 16-bit image
+    let input_file_path = "./test-16-bit.png";
+    let output_file_path = "./test-16-bit-cropped.png";
+    let downconverted_file_path = "./test-16-bit-cropped.jpg";
+    let pixels = ImageBuffer::from_fn(64, 64, |x, y| Rgb([(x * 1000) as u16, (y * 1000) as u16, 1]));
+    let _ = DynamicImage::ImageRgb16(pixels).save(input_file_path);
+
+    // 2) crop and resize into a format that supports 16-bit samples
+    let mut params = CropParameters {
+      input_file_path: String::from(input_file_path),
+      output_file_path: Some(String::from(output_file_path)),
+      dimensions: Some(CropDimensions {
+        size: ImageSize { width: 32, height: 32 },
+        top_left_point: Point { x: 8, y: 8 },
+      }),
+      resize: Some(ImageSize { width: 16, height: 16 }),
+      ..Default::default()
+    };
+    let result = crop_image(&params).unwrap();
+    assert!(result.warnings.is_empty());
+    let cropped = image::open(output_file_path).unwrap();
+    assert_eq!(cropped.color(), ColorType::Rgb16);
+    assert_eq!(cropped.dimensions(), (16, 16));
+
+    // 3) crop into a format without 16-bit support
+    params.format = Some(String::from("jpg"));
+    let result = crop_image(&params).unwrap();
+    assert_eq!(result.file_path, downconverted_file_path);
+    assert_eq!(result.warnings.len(), 1);
+    assert_eq!(image::open(downconverted_file_path).unwrap().color(), ColorType::Rgb8);
+
+    // 4) cleanup
+    fs::remove_file(input_file_path).unwrap();
+    fs::remove_file(output_file_path).unwrap();
+    fs::remove_file(downconverted_file_path).unwrap();
+  }
+
+  #[test]
+  pub fn test_crop_image_without_dimensions() {
+    // 1) prepare dummy image
+    let input_file_path = "./test-whole.png";
+    let output_file_path = "./test-whole-resized.png";
+    create_dummy_image(48, 32).save(input_file_path).unwrap();
+
+    // 2) keep the whole image and only resize it
+    let params = CropParameters {
+      input_file_path: String::from(input_file_path),
+      output_file_path: Some(String::from(output_file_path)),
+      resize: Some(ImageSize { width: 24, height: 16 }),
+      ..Default::default()
+    };
+    crop_image(&params).unwrap();
+    assert_eq!(image::open(output_file_path).unwrap().dimensions(), (24, 16));
+
+    // 3) cleanup
+    fs::remove_file(input_file_path).unwrap();
+    fs::remove_file(output_file_path).unwrap();
+  }
+
+  #[test]
+  pub fn test_crop_image_formats() {
+    for extension in ["tiff", "ico", "tga", "qoi", "webp"] {
+      // 1) prepare dummy image in the tested format
+      let input_file_path = format!("./test-format.{}", extension);
+      let output_file_path = format!("./test-format-cropped.{}", extension);
+      create_dummy_image(64, 64).save(&input_file_path).unwrap();
+
+      // 2) crop it and keep the format
+      let params = CropParameters {
+        input_file_path: input_file_path.clone(),
+        output_file_path: Some(output_file_path.clone()),
+        dimensions: Some(CropDimensions {
+          size: ImageSize { width: 20, height: 10 },
+          top_left_point: Point { x: 5, y: 5 },
+        }),
+        ..Default::default()
+      };
+      let result = crop_image(&params).unwrap();
+
+      // 3) test if resulting image dimensions match
+      let cropped = image::open(&result.file_path).unwrap();
+      assert_eq!(cropped.dimensions(), (20, 10));
+
+      // 4) cleanup
+      fs::remove_file(input_file_path).unwrap();
+      fs::remove_file(output_file_path).unwrap();
+    }
+  }
+
+  #[test]
+  pub fn test_crop_image_region() {
+    // 1) prepare dummy images
+    let png_file_path = "./test-region.png";
+    let jpg_file_path = "./test-region.jpg";
+    let png_output_path = "./test-region-cropped.png";
+    let jpg_output_path = "./test-region-cropped.jpg";
+    let dummy_image = create_dummy_image(300, 200);
+    dummy_image.save(png_file_path).unwrap();
+    dummy_image.to_rgb8().save(jpg_file_path).unwrap();
+
+    // 2) rows streamed from the PNG match a crop of the fully decoded image
+    let mut params = CropParameters {
+      input_file_path: String::from(png_file_path),
+      output_file_path: Some(String::from(png_output_path)),
+      dimensions: Some(CropDimensions {
+        size: ImageSize { width: 120, height: 80 },
+        top_left_point: Point { x: 100, y: 60 },
+      }),
+      ..Default::default()
+    };
+    crop_image(&params).unwrap();
+    let cropped = image::open(png_output_path).unwrap();
+    assert_eq!(cropped, dummy_image.crop_imm(100, 60, 120, 80));
+
+    // 3) JPEG decoded at a reduced scale still ends up with the requested size
+    params.input_file_path = String::from(jpg_file_path);
+    params.output_file_path = Some(String::from(jpg_output_path));
+    params.resize = Some(ImageSize { width: 30, height: 20 });
+    crop_image(&params).unwrap();
+    assert_eq!(image::open(jpg_output_path).unwrap().dimensions(), (30, 20));
+
+    // 4) cleanup
+    fs::remove_file(png_file_path).unwrap();
+    fs::remove_file(jpg_file_path).unwrap();
+    fs::remove_file(png_output_path).unwrap();
+    fs::remove_file(jpg_output_path).unwrap();
+  }
+
+  #[test]
+  pub fn test_crop_image_limits() {
+    // 1) prepare dummy images
+    let png_file_path = "./test-limits.png";
+    let bmp_file_path = "./test-limits.bmp";
+    let output_file_path = "./test-limits-cropped.png";
+    let dummy_image = create_dummy_image(300, 200);
+    dummy_image.save(png_file_path).unwrap();
+    dummy_image.save(bmp_file_path).unwrap();
+
+    // 2) images larger than the dimension limits are rejected before decoding
+    let mut params = CropParameters {
+      input_file_path: String::from(png_file_path),
+      output_file_path: Some(String::from(output_file_path)),
+      dimensions: Some(CropDimensions {
+        size: ImageSize { width: 50, height: 50 },
+        top_left_point: Point { x: 0, y: 0 },
+      }),
+      limits: Some(ImageLimits { max_width: 200, ..Default::default() }),
+      ..Default::default()
+    };
+    let result = crop_image(&params);
+    assert_eq!(result.err(), Some(String::from("The image width (300px) exceeds the limit of 200px.")));
+
+    params.limits = Some(ImageLimits { max_pixels: 50_000, ..Default::default() });
+    let result = crop_image(&params);
+    assert_eq!(
+      result.err(),
+      Some(String::from("The image pixel count (60000) exceeds the limit of 50000 pixels."))
+    );
+
+    // 3) decoders can't allocate more memory than allowed
+    params.limits = Some(ImageLimits { max_alloc: 1000, ..Default::default() });
+    assert!(crop_image(&params).unwrap_err().contains("exceeds the limit of 1000 bytes"));
+    params.input_file_path = String::from(bmp_file_path);
+    assert!(crop_image(&params).unwrap_err().contains("limit of 1000 bytes"));
+
+    // 4) the watermark is checked against the limits as well
+    let small_file_path = "./test-limits-small.png";
+    create_dummy_image(100, 100).save(small_file_path).unwrap();
+    params.input_file_path = String::from(small_file_path);
+    params.limits = Some(ImageLimits { max_pixels: 50_000, ..Default::default() });
+    params.watermark = Some(Watermark { file_path: Some(String::from(png_file_path)), ..Default::default() });
+    assert_eq!(
+      crop_image(&params).err(),
+      Some(String::from("The image pixel count (60000) exceeds the limit of 50000 pixels."))
+    );
+
+    // 5) cleanup
+    fs::remove_file(png_file_path).unwrap();
+    fs::remove_file(bmp_file_path).unwrap();
+    fs::remove_file(small_file_path).unwrap();
+    assert!(!std::path::Path::new(output_file_path).exists());
+  }
 }
@@ -1,38 +1,741 @@
+use std::{ env, fs, path::{ Path, PathBuf }, process::{ self, Command } };
+use drag_and_crop::utils::ffmpeg::ffmpeg::get_ffmpeg;
+
+/// Test pattern with a tone, 6 seconds long.
+const TEST_VIDEO_INPUTS: [&str; 8] = [
+  "-f",
+  "lavfi",
+  "-i",
+  "testsrc2=size=320x240:rate=25:duration=6",
+  "-f",
+  "lavfi",
+  "-i",
+  "sine=frequency=440:duration=6",
+];
+
+/// Temporary folder of a test, removed with its files when the test ends.
+struct TestFolder {
+  path: PathBuf,
+}
+
+impl TestFolder {
+  fn new(name: &str) -> Self {
+    let path = env::temp_dir().join(format!("drag-and-crop-{}-{}", name, process::id()));
+    let _ = fs::remove_dir_all(&path);
+    fs::create_dir_all(&path).unwrap();
+    TestFolder { path }
+  }
+
+  fn file(&self, file_name: &str) -> String {
+    self.path.join(file_name).to_string_lossy().to_string()
+  }
+}
+
+impl Drop for TestFolder {
+  fn drop(&mut self) {
+    let _ = fs::remove_dir_all(&self.path);
+  }
+}
+
+/// Creates the 320x240 test video (25 fps, a keyframe every 2 seconds, h264 and aac).
+/// `output_args` are added before the output file, e.g. filters or metadata.
+/// The video tests are ignored by default, they fail if ffmpeg with libx264 and aac isn't installed.
+fn create_test_video(folder: &TestFolder, file_name: &str, output_args: &[&str]) -> String {
+  create_video(folder, file_name, &TEST_VIDEO_INPUTS, output_args)
+}
+
+fn create_video(folder: &TestFolder, file_name: &str, inputs: &[&str], output_args: &[&str]) -> String {
+  let ffmpeg = get_ffmpeg().expect("ffmpeg is required by the video tests");
+  let has_libx264 = ffmpeg.video_encoders.iter().any(|name| name == "libx264");
+  let has_aac = ffmpeg.audio_encoders.iter().any(|name| name == "aac");
+  assert!(has_libx264 && has_aac, "The video tests require the libx264 and aac encoders.");
+  let file_path = folder.file(file_name);
+  let status = Command::new(&ffmpeg.ffmpeg_path)
+    .args(["-hide_banner", "-loglevel", "error", "-y"])
+    .args(inputs)
+    .args(["-c:v", "libx264", "-pix_fmt", "yuv420p", "-g", "50", "-sc_threshold", "0", "-c:a", "aac"])
+    .args(output_args)
+    .arg(&file_path)
+    .status()
+    .unwrap();
+  assert!(status.success(), "The test video could not be created.");
+  file_path
+}
+
+/// Decodes the whole video and returns the errors that ffmpeg reported.
+fn get_decoding_errors(file_path: &str) -> String {
+  let ffmpeg = get_ffmpeg().unwrap();
+  let output = Command::new(&ffmpeg.ffmpeg_path)
+    .args(["-hide_banner", "-v", "error", "-i", file_path, "-f", "null", "-"])
+    .output()
+    .unwrap();
+  String::from_utf8_lossy(&output.stderr).to_string()
+}
+
+/// Returns the title from the container metadata, or an empty string.
+fn get_title(file_path: &str) -> String {
+  let ffmpeg = get_ffmpeg().unwrap();
+  let output = Command::new(&ffmpeg.ffprobe_path)
+    .args(["-v", "error", "-show_entries", "format_tags=title", "-of", "default=noprint_wrappers=1:nokey=1", file_path])
+    .output()
+    .unwrap();
+  String::from_utf8_lossy(&output.stdout).trim().to_string()
+}
+
 #[cfg(test)]
 pub mod tests {
-  use std::fs;
-  use drag_and_crop::{ crop_video, CropParameters, CropDimensions, ImageSize, Point };
+  use drag_and_crop::{ crop_video, crop_video_with_progress, detect_black_bars, detect_scenes, extract_poster };
+  use drag_and_crop::{
+    AnimationOptions,
+    AudioCodec,
+    AudioMode,
+    AudioOptions,
+    CropDimensions,
+    CropKeyframe,
+    CropParameters,
+    DimensionPolicy,
+    EncodingProfile,
+    HlsOptions,
+    HlsRendition,
+    ImageSize,
+    Interpolation,
+    Point,
+    PosterParameters,
+    ReframeAspect,
+    SceneParameters,
+    ThumbnailOptions,
+    TrimMode,
+    VideoCodec,
+  };
+  use drag_and_crop::utils::cancel::cancel::CancelToken;
+  use drag_and_crop::utils::ffmpeg::ffmpeg::{ detect_ffmpeg, run_ffmpeg, FfmpegConfig };
+  use drag_and_crop::utils::limits::limits::ProcessLimits;
+  use drag_and_crop::utils::probe::probe::probe_video;
+  use drag_and_crop::utils::progress::progress::ProgressParser;
+
+  use super::*;
+
+  #[test]
+  #[ignore = "requires ffmpeg with libx264 and aac"]
+  pub fn test_crop_video() {
+    let folder = TestFolder::new("crop");
+    let input_file_path = create_test_video(&folder, "input.mp4", &[]);
+
+    // the crop has to fit into the probed frame
+    let params = CropParameters {
+      input_file_path: input_file_path.clone(),
+      output_file_path: Some(folder.file("output.mp4")),
+      dimensions: Some(CropDimensions {
+        size: ImageSize { width: 200, height: 200 },
+        top_left_point: Point { x: 200, y: 20 },
+      }),
+      ..Default::default()
+    };
+    assert_eq!(crop_video(&params).unwrap_err(), "The output size is larger than the input video size.");
+
+    let params = CropParameters {
+      dimensions: Some(CropDimensions {
+        size: ImageSize { width: 160, height: 120 },
+        top_left_point: Point { x: 80, y: 60 },
+      }),
+      ..params
+    };
+    let result = crop_video(&params).unwrap();
+    assert_eq!(result.file_path, folder.file("output.mp4"));
+    let output = probe_video(&result.file_path, &CancelToken::new()).unwrap();
+    assert_eq!((output.width, output.height), (160, 120));
+    assert!((output.duration.unwrap() - 6.0).abs() < 0.1);
+  }
+
+  #[test]
+  #[ignore = "requires ffmpeg with libx264 and aac"]
+  pub fn test_trim_video_accurately() {
+    let folder = TestFolder::new("trim-accurately");
+    let input_file_path = create_test_video(&folder, "input.mp4", &[]);
+
+    // the video isn't cropped, and the start is between the keyframes at 0s and 2s, so the first second is re-encoded
+    let params = CropParameters {
+      input_file_path: input_file_path.clone(),
+      output_file_path: Some(folder.file("output.mp4")),
+      start: Some(1.0),
+      end: Some(5.0),
+      trim_mode: Some(TrimMode::Accurate),
+      ..Default::default()
+    };
+    let result = crop_video(&params).unwrap();
+    assert_eq!(result.start, Some(1.0));
+
+    // the encoded head has the parameters of the copied tail, so the joined stream decodes cleanly
+    let cancel = CancelToken::new();
+    let input = probe_video(&input_file_path, &cancel).unwrap();
+    let output = probe_video(&result.file_path, &cancel).unwrap();
+    assert_eq!((output.width, output.height), (320, 240));
+    assert!((output.duration.unwrap() - 4.0).abs() < 0.1);
+    assert_eq!(output.profile, input.profile);
+    assert_eq!(output.level, input.level);
+    assert_eq!(output.pixel_format, input.pixel_format);
+    assert_eq!(output.time_base, input.time_base);
+    assert_eq!(get_decoding_errors(&result.file_path), "");
+  }
+
+  #[test]
+  #[ignore = "requires ffmpeg"]
+  pub fn test_detect_configured_ffmpeg() {
+    let ffmpeg = get_ffmpeg().expect("ffmpeg is required by the video tests");
+
+    // the configured paths take precedence over the environment and the PATH
+    let config = FfmpegConfig {
+      ffmpeg_path: Some(ffmpeg.ffmpeg_path.clone()),
+      ffprobe_path: Some(ffmpeg.ffprobe_path.clone()),
+      ..Default::default()
+    };
+    let detected = detect_ffmpeg(&config).unwrap();
+    assert_eq!(detected.version, ffmpeg.version);
+    assert_eq!(detected.ffprobe_version, ffmpeg.ffprobe_version);
+    assert!(detected.filters.iter().any(|name| name == "crop"));
+
+    let config = FfmpegConfig { ffmpeg_path: Some(String::from("./missing/ffmpeg")), ..config };
+    assert!(detect_ffmpeg(&config).is_err());
+  }
+
+  #[test]
+  #[ignore = "requires ffmpeg with libx264 and aac"]
+  pub fn test_crop_video_progress() {
+    let folder = TestFolder::new("progress");
+    let input_file_path = create_test_video(&folder, "input.mp4", &[]);
+
+    let params = CropParameters {
+      input_file_path,
+      output_file_path: Some(folder.file("output.mp4")),
+      dimensions: Some(CropDimensions {
+        size: ImageSize { width: 160, height: 120 },
+        top_left_point: Point { x: 0, y: 0 },
+      }),
+      ..Default::default()
+    };
+    let mut percents = Vec::new();
+    crop_video_with_progress(&params, &CancelToken::new(), |progress| percents.push(progress.percent)).unwrap();
+
+    // the percentage only grows and ends at 100
+    assert!(!percents.is_empty());
+    assert!(percents.windows(2).all(|pair| pair[0] <= pair[1]));
+    assert_eq!(percents.last(), Some(&100.0));
+  }
+
+  #[test]
+  #[ignore = "requires ffmpeg with libx264 and aac"]
+  pub fn test_cancel_video_crop() {
+    let folder = TestFolder::new("cancel");
+    let input_file_path = create_test_video(&folder, "input.mp4", &[]);
+
+    // the job is cancelled as soon as ffmpeg reports progress, like a client that disconnects
+    let params = CropParameters {
+      input_file_path,
+      output_file_path: Some(folder.file("output.mp4")),
+      dimensions: Some(CropDimensions {
+        size: ImageSize { width: 160, height: 120 },
+        top_left_point: Point { x: 0, y: 0 },
+      }),
+      ..Default::default()
+    };
+    let cancel = CancelToken::new();
+    let result = crop_video_with_progress(&params, &cancel, |_| cancel.cancel());
+    assert_eq!(result.unwrap_err(), "The video processing was cancelled.");
+    assert!(!Path::new(&folder.file("output.mp4")).exists());
+  }
+
+  #[test]
+  #[ignore = "requires ffmpeg with libx264 and aac"]
+  pub fn test_trim_cropped_video() {
+    let folder = TestFolder::new("trim");
+    let input_file_path = create_test_video(&folder, "input.mp4", &[]);
+
+    let params = CropParameters {
+      input_file_path,
+      output_file_path: Some(folder.file("output.mp4")),
+      dimensions: Some(CropDimensions {
+        size: ImageSize { width: 160, height: 120 },
+        top_left_point: Point { x: 0, y: 0 },
+      }),
+      start: Some(1.0),
+      duration: Some(2.0),
+      ..Default::default()
+    };
+    let result = crop_video(&params).unwrap();
+    assert_eq!((result.start, result.end), (Some(1.0), Some(3.0)));
+    let output = probe_video(&result.file_path, &CancelToken::new()).unwrap();
+    assert!((output.duration.unwrap() - 2.0).abs() < 0.1);
+
+    // the end can't be after the end of the video
+    let params = CropParameters { output_file_path: Some(folder.file("too-long.mp4")), duration: Some(10.0), ..params };
+    assert!(crop_video(&params).unwrap_err().starts_with("The end time (11s) is after the end of the video"));
+  }
+
+  #[test]
+  #[ignore = "requires ffmpeg with libx264 and aac"]
+  pub fn test_trim_video_at_keyframe() {
+    let folder = TestFolder::new("trim-keyframe");
+    let input_file_path = create_test_video(&folder, "input.mp4", &[]);
+
+    // the keyframes are at 0s, 2s and 4s, so the copy starts one second early
+    let params = CropParameters {
+      input_file_path: input_file_path.clone(),
+      output_file_path: Some(folder.file("output.mp4")),
+      start: Some(3.0),
+      end: Some(5.0),
+      ..Default::default()
+    };
+    let result = crop_video(&params).unwrap();
+    let (start, end) = (result.start.unwrap(), result.end.unwrap());
+    assert!((start - 2.0).abs() < 0.05);
+    assert_eq!(end, 5.0);
+
+    // the streams are copied, not re-encoded
+    let cancel = CancelToken::new();
+    let input = probe_video(&input_file_path, &cancel).unwrap();
+    let output = probe_video(&result.file_path, &cancel).unwrap();
+    assert!((output.duration.unwrap() - (end - start)).abs() < 0.1);
+    assert_eq!(output.profile, input.profile);
+    assert_eq!(output.level, input.level);
+    assert_eq!(get_decoding_errors(&result.file_path), "");
+  }
+
+  #[test]
+  #[ignore = "requires ffmpeg with libx264 and aac"]
+  pub fn test_encode_cropped_video() {
+    let folder = TestFolder::new("encode");
+    let input_file_path = create_test_video(&folder, "input.mp4", &[]);
+
+    let params = CropParameters {
+      input_file_path,
+      output_file_path: Some(folder.file("output.mp4")),
+      dimensions: Some(CropDimensions {
+        size: ImageSize { width: 160, height: 120 },
+        top_left_point: Point { x: 0, y: 0 },
+      }),
+      encoding: Some(EncodingProfile {
+        codec: Some(VideoCodec::H264),
+        crf: Some(30),
+        preset: Some(String::from("veryfast")),
+        container: Some(String::from("mkv")),
+        ..Default::default()
+      }),
+      ..Default::default()
+    };
+    let result = crop_video(&params).unwrap();
+    assert_eq!(result.file_path, folder.file("output.mkv"));
+    let output = probe_video(&result.file_path, &CancelToken::new()).unwrap();
+    assert_eq!(output.codec_name.as_deref(), Some("h264"));
+    assert_eq!((output.width, output.height), (160, 120));
+  }
+
+  #[test]
+  #[ignore = "requires ffmpeg with libx264 and aac"]
+  pub fn test_encode_video_from_other_container() {
+    let folder = TestFolder::new("encode-avi");
+    let input_file_path = create_test_video(&folder, "input.avi", &[]);
+
+    // avi can't store the encoded video, so it's stored in mp4
+    let params = CropParameters {
+      input_file_path,
+      output_file_path: Some(folder.file("output.avi")),
+      dimensions: Some(CropDimensions {
+        size: ImageSize { width: 160, height: 120 },
+        top_left_point: Point { x: 0, y: 0 },
+      }),
+      encoding: Some(EncodingProfile::default()),
+      ..Default::default()
+    };
+    let result = crop_video(&params).unwrap();
+    assert_eq!(result.file_path, folder.file("output.mp4"));
+    let output = probe_video(&result.file_path, &CancelToken::new()).unwrap();
+    assert_eq!(output.codec_name.as_deref(), Some("h264"));
+  }
+
+  #[test]
+  #[ignore = "requires ffmpeg with libx264 and aac"]
+  pub fn test_crop_video_audio() {
+    let folder = TestFolder::new("audio");
+    let input_file_path = create_test_video(&folder, "input.mp4", &[]);
+
+    let cancel = CancelToken::new();
+    let params = CropParameters {
+      input_file_path,
+      output_file_path: Some(folder.file("removed.mp4")),
+      dimensions: Some(CropDimensions {
+        size: ImageSize { width: 160, height: 120 },
+        top_left_point: Point { x: 0, y: 0 },
+      }),
+      audio: Some(AudioOptions { mode: Some(AudioMode::Remove), ..Default::default() }),
+      ..Default::default()
+    };
+    let result = crop_video(&params).unwrap();
+    assert!(probe_video(&result.file_path, &cancel).unwrap().audio_tracks.is_empty());
+
+    let params = CropParameters {
+      output_file_path: Some(folder.file("encoded.mp4")),
+      audio: Some(AudioOptions { codec: Some(AudioCodec::Aac), bitrate: Some(64), ..Default::default() }),
+      ..params
+    };
+    let result = crop_video(&params).unwrap();
+    let tracks = probe_video(&result.file_path, &cancel).unwrap().audio_tracks;
+    assert_eq!(tracks.len(), 1);
+    assert_eq!(tracks[0].codec_name.as_deref(), Some("aac"));
+  }
+
+  #[test]
+  #[ignore = "requires ffmpeg with libx264 and aac"]
+  pub fn test_crop_video_with_keyframes() {
+    let folder = TestFolder::new("keyframes");
+    let input_file_path = create_test_video(&folder, "input.mp4", &[]);
+
+    // the crop pans from the top left to the bottom right corner
+    let keyframes = vec![
+      CropKeyframe { time: 0.0, top_left_point: Point { x: 0, y: 0 }, interpolation: None },
+      CropKeyframe { time: 4.0, top_left_point: Point { x: 160, y: 120 }, interpolation: Some(Interpolation::Ease) },
+    ];
+    let params = CropParameters {
+      input_file_path,
+      output_file_path: Some(folder.file("output.mp4")),
+      dimensions: Some(CropDimensions {
+        size: ImageSize { width: 160, height: 120 },
+        top_left_point: Point { x: 0, y: 0 },
+      }),
+      keyframes: Some(keyframes.clone()),
+      ..Default::default()
+    };
+    let result = crop_video(&params).unwrap();
+    let output = probe_video(&result.file_path, &CancelToken::new()).unwrap();
+    assert_eq!((output.width, output.height), (160, 120));
+    assert_eq!(get_decoding_errors(&result.file_path), "");
+
+    // the crop has to stay within the frame at every keyframe
+    let mut keyframes = keyframes;
+    keyframes[1].top_left_point = Point { x: 200, y: 120 };
+    let params = CropParameters {
+      output_file_path: Some(folder.file("out-of-bounds.mp4")),
+      keyframes: Some(keyframes),
+      ..params
+    };
+    assert_eq!(
+      crop_video(&params).unwrap_err(),
+      "The output size is larger than the input video size (keyframe at 4s)."
+    );
+  }
+
+  #[test]
+  #[ignore = "requires ffmpeg with libx264 and aac"]
+  pub fn test_reframe_video() {
+    let folder = TestFolder::new("reframe");
+    let input_file_path = create_test_video(&folder, "input.mp4", &[]);
+
+    let params = CropParameters {
+      input_file_path,
+      output_file_path: Some(folder.file("square.mp4")),
+      auto_reframe: Some(ReframeAspect::Square),
+      ..Default::default()
+    };
+    let result = crop_video(&params).unwrap();
+    let size = result.size.unwrap();
+    assert_eq!((size.width, size.height), (240, 240));
+    let output = probe_video(&result.file_path, &CancelToken::new()).unwrap();
+    assert_eq!((output.width, output.height), (240, 240));
+
+    // 240 * 9 / 16 = 135, rounded down to an even width
+    let params = CropParameters {
+      output_file_path: Some(folder.file("vertical.mp4")),
+      auto_reframe: Some(ReframeAspect::Vertical),
+      ..params
+    };
+    let result = crop_video(&params).unwrap();
+    let output = probe_video(&result.file_path, &CancelToken::new()).unwrap();
+    assert_eq!((output.width, output.height), (134, 240));
+  }
 
-  #[tokio::test]
-  pub async fn test_crop_video() {
-    // 1) download dummy video
-    const VIDEO_URL: &str = "https://i.imgur.com/MSMEI6H.mp4";
-    let client = reqwest::Client::builder().build().unwrap();
-    let response = client.get(VIDEO_URL).send().await.unwrap();
-    let input_file_name = "./test.mp4";
-    let bytes = response.bytes().await.unwrap();
-    let _ = fs::write(&input_file_name, &bytes);
+  #[test]
+  #[ignore = "requires ffmpeg with libx264 and aac"]
+  pub fn test_convert_video_to_animation() {
+    let folder = TestFolder::new("animation");
+    let input_file_path = create_test_video(&folder, "input.mp4", &[]);
 
-    // 2) prepare cropping parameters
-    const WANTED_WIDTH: u32 = 500;
-    const WANTED_HEIGHT: u32 = 400;
-    let output_file_name = "./test-cropped.mp4";
+    // the 200px wide crop is scaled down to the maximum width
+    let params = CropParameters {
+      input_file_path,
+      output_file_path: Some(folder.file("output.mp4")),
+      dimensions: Some(CropDimensions {
+        size: ImageSize { width: 200, height: 100 },
+        top_left_point: Point { x: 0, y: 0 },
+      }),
+      start: Some(1.0),
+      duration: Some(1.0),
+      animation: Some(AnimationOptions { max_width: Some(100), ..Default::default() }),
+      ..Default::default()
+    };
+    let result = crop_video(&params).unwrap();
+    assert_eq!(result.file_path, folder.file("output.gif"));
+    assert_eq!(image::image_dimensions(&result.file_path).unwrap(), (100, 50));
+  }
+
+  #[test]
+  #[ignore = "requires ffmpeg with libx264 and aac"]
+  pub fn test_extract_poster() {
+    let folder = TestFolder::new("poster");
+    let input_file_path = create_test_video(&folder, "input.mp4", &[]);
+
+    let cancel = CancelToken::new();
+    let params = PosterParameters {
+      input_file_path,
+      output_file_path: Some(folder.file("poster.jpg")),
+      time: Some(1.0),
+      dimensions: Some(CropDimensions {
+        size: ImageSize { width: 160, height: 120 },
+        top_left_point: Point { x: 80, y: 60 },
+      }),
+      ..Default::default()
+    };
+    let result = extract_poster(&params, &cancel).unwrap();
+    assert_eq!(result.start, Some(1.0));
+    assert_eq!(image::image_dimensions(&result.file_path).unwrap(), (160, 120));
 
+    // without a time, the sharpest frame of the whole video is used
+    let params = PosterParameters {
+      output_file_path: Some(folder.file("sharpest.png")),
+      time: None,
+      dimensions: None,
+      format: Some(String::from("png")),
+      ..params
+    };
+    let result = extract_poster(&params, &cancel).unwrap();
+    assert!(result.start.is_some_and(|time| (0.0..6.0).contains(&time)));
+    assert_eq!(image::image_dimensions(&result.file_path).unwrap(), (320, 240));
+  }
+
+  #[test]
+  #[ignore = "requires ffmpeg with libx264 and aac"]
+  pub fn test_create_video_thumbnails() {
+    let folder = TestFolder::new("thumbnails");
+    let input_file_path = create_test_video(&folder, "input.mp4", &[]);
+
+    // two 160x80 thumbnails of the 6 second video, next to each other
     let params = CropParameters {
-      input_file_path: String::from(input_file_name),
-      output_file_path: Some(String::from(output_file_name)),
-      dimensions: CropDimensions {
-        size: ImageSize { width: WANTED_WIDTH, height: WANTED_HEIGHT },
-        top_left_point: Point { x: 250, y: 300 },
-      },
+      input_file_path,
+      output_file_path: Some(folder.file("output.mp4")),
+      dimensions: Some(CropDimensions {
+        size: ImageSize { width: 200, height: 100 },
+        top_left_point: Point { x: 0, y: 0 },
+      }),
+      thumbnails: Some(ThumbnailOptions { interval: Some(4.0), ..Default::default() }),
+      ..Default::default()
     };
     let result = crop_video(&params).unwrap();
+    let thumbnails = result.thumbnails.unwrap();
+    assert_eq!(thumbnails.sprite_file_path, folder.file("output-sprite.jpg"));
+    assert_eq!(image::image_dimensions(&thumbnails.sprite_file_path).unwrap(), (320, 80));
+
+    let vtt = fs::read_to_string(&thumbnails.vtt_file_path).unwrap();
+    assert!(vtt.starts_with("WEBVTT\n\n00:00:00.000 --> 00:00:04.000\noutput-sprite.jpg#xywh=0,0,160,80\n"));
+    assert!(vtt.contains("\noutput-sprite.jpg#xywh=160,0,160,80\n"));
+  }
+
+  #[test]
+  #[ignore = "requires ffmpeg with libx264 and aac"]
+  pub fn test_package_video_for_hls() {
+    let folder = TestFolder::new("hls");
+    let input_file_path = create_test_video(&folder, "input.mp4", &[]);
 
-    // 3) test if result file matches
-    assert_eq!(result.as_str(), output_file_name);
+    let params = CropParameters {
+      input_file_path,
+      output_file_path: Some(folder.file("output.mp4")),
+      dimensions: Some(CropDimensions {
+        size: ImageSize { width: 320, height: 240 },
+        top_left_point: Point { x: 0, y: 0 },
+      }),
+      hls: Some(HlsOptions {
+        renditions: Some(vec![
+          HlsRendition { height: 240, bitrate: Some(400) },
+          HlsRendition { height: 120, bitrate: None },
+        ]),
+        segment_duration: Some(2.0),
+      }),
+      ..Default::default()
+    };
+    let result = crop_video(&params).unwrap();
+    let hls = result.hls.unwrap();
+    assert_eq!(hls.master_playlist_path, folder.file("output-hls/master.m3u8"));
+    assert!(hls.file_paths.iter().all(|file_path| Path::new(file_path).exists()));
+    // the master playlist, two rendition playlists and their segments
+    assert!(hls.file_paths.len() > 3);
 
-    // 4) cleanup
-    let _ = fs::remove_file(input_file_name).unwrap();
-    let _ = fs::remove_file(output_file_name).unwrap();
+    let master_playlist = fs::read_to_string(&hls.master_playlist_path).unwrap();
+    assert!(master_playlist.starts_with("#EXTM3U"));
+    assert!(master_playlist.contains("RESOLUTION=320x240"));
+    assert!(master_playlist.contains("RESOLUTION=160x120"));
+  }
+
+  #[test]
+  #[ignore = "requires ffmpeg with libx264 and aac"]
+  pub fn test_crop_anamorphic_video() {
+    let folder = TestFolder::new("anamorphic");
+    let input_file_path = create_test_video(&folder, "input.mp4", &["-vf", "setsar=2/1"]);
+
+    // the coded 320x240 frame is displayed as 640x240
+    let cancel = CancelToken::new();
+    let input = probe_video(&input_file_path, &cancel).unwrap();
+    assert_eq!(input.get_display_size(), (640, 240));
+
+    // the crop is given on the displayed frame, the output has square pixels
+    let params = CropParameters {
+      input_file_path,
+      output_file_path: Some(folder.file("output.mp4")),
+      dimensions: Some(CropDimensions {
+        size: ImageSize { width: 320, height: 240 },
+        top_left_point: Point { x: 320, y: 0 },
+      }),
+      ..Default::default()
+    };
+    let result = crop_video(&params).unwrap();
+    let output = probe_video(&result.file_path, &cancel).unwrap();
+    assert!(output.has_square_pixels());
+    assert_eq!((output.width, output.height), (320, 240));
+  }
+
+  #[test]
+  #[ignore = "requires ffmpeg with libx264 and aac"]
+  pub fn test_crop_video_with_odd_dimensions() {
+    let folder = TestFolder::new("odd-dimensions");
+    let input_file_path = create_test_video(&folder, "input.mp4", &[]);
+
+    // yuv420p needs even dimensions, the last row and column are dropped by default
+    let cancel = CancelToken::new();
+    let params = CropParameters {
+      input_file_path,
+      output_file_path: Some(folder.file("rounded.mp4")),
+      dimensions: Some(CropDimensions {
+        size: ImageSize { width: 201, height: 101 },
+        top_left_point: Point { x: 10, y: 10 },
+      }),
+      ..Default::default()
+    };
+    let result = crop_video(&params).unwrap();
+    let size = result.size.unwrap();
+    assert_eq!((size.width, size.height), (200, 100));
+    let output = probe_video(&result.file_path, &cancel).unwrap();
+    assert_eq!((output.width, output.height), (200, 100));
+
+    let params = CropParameters {
+      output_file_path: Some(folder.file("padded.mp4")),
+      dimension_policy: Some(DimensionPolicy::Pad),
+      ..params
+    };
+    let result = crop_video(&params).unwrap();
+    let size = result.size.unwrap();
+    assert_eq!((size.width, size.height), (202, 102));
+    let output = probe_video(&result.file_path, &cancel).unwrap();
+    assert_eq!((output.width, output.height), (202, 102));
+  }
+
+  #[test]
+  #[ignore = "requires ffmpeg with libx264 and aac"]
+  pub fn test_crop_video_metadata() {
+    let folder = TestFolder::new("metadata");
+    let metadata = ["-metadata", "title=Test pattern", "-metadata:s:a:0", "language=eng"];
+    let input_file_path = create_test_video(&folder, "input.mp4", &metadata);
+    let cancel = CancelToken::new();
+
+    // the container metadata is kept by default
+    let params = CropParameters {
+      input_file_path,
+      output_file_path: Some(folder.file("kept.mp4")),
+      dimensions: Some(CropDimensions {
+        size: ImageSize { width: 160, height: 120 },
+        top_left_point: Point { x: 0, y: 0 },
+      }),
+      ..Default::default()
+    };
+    let result = crop_video(&params).unwrap();
+    assert_eq!(get_title(&result.file_path), "Test pattern");
+
+    let params = CropParameters {
+      output_file_path: Some(folder.file("removed.mp4")),
+      keep_metadata: Some(false),
+      ..params
+    };
+    let result = crop_video(&params).unwrap();
+    assert_eq!(get_title(&result.file_path), "");
+    // the stream metadata is kept
+    let output = probe_video(&result.file_path, &cancel).unwrap();
+    assert_eq!(output.audio_tracks[0].language.as_deref(), Some("eng"));
+  }
+
+  #[test]
+  #[ignore = "requires ffmpeg"]
+  pub fn test_ffmpeg_error_output() {
+    get_ffmpeg().expect("ffmpeg is required by the video tests");
+
+    // the error output is read while ffmpeg runs and only its end is kept
+    let folder = TestFolder::new("ffmpeg-error");
+    let args = [String::from("-i"), folder.file("missing.mp4"), folder.file("output.mp4")];
+    let error = run_ffmpeg(&args, ProgressParser::new(None, None), &CancelToken::new(), |_| ()).unwrap_err();
+    assert!(error.contains("No such file or directory"));
+    assert!(error.len() <= ProcessLimits::from_env().max_error_output + 100);
+    assert!(!Path::new(&folder.file("output.mp4")).exists());
+  }
+
+  #[test]
+  #[ignore = "requires ffmpeg with libx264 and aac"]
+  pub fn test_detect_scenes() {
+    let folder = TestFolder::new("scenes");
+    // the test pattern cuts to color bars after 3 seconds
+    let inputs = [
+      "-f",
+      "lavfi",
+      "-i",
+      "testsrc2=size=320x240:rate=25:duration=3",
+      "-f",
+      "lavfi",
+      "-i",
+      "smptebars=size=320x240:rate=25:duration=3",
+    ];
+    let output_args = ["-filter_complex", "[0:v][1:v]concat=n=2:v=1:a=0"];
+    let input_file_path = create_video(&folder, "input.mp4", &inputs, &output_args);
+
+    let params = SceneParameters {
+      input_file_path,
+      output_file_path: Some(folder.file("output.jpg")),
+      ..Default::default()
+    };
+    let scenes = detect_scenes(&params, &CancelToken::new()).unwrap();
+    assert_eq!(scenes.len(), 1);
+    assert!((scenes[0].time - 3.0).abs() < 0.1);
+    assert!(scenes[0].score > 0.4);
+    assert_eq!(scenes[0].thumbnail, folder.file("output-scene-001.jpg"));
+    assert_eq!(image::image_dimensions(&scenes[0].thumbnail).unwrap(), (160, 120));
+  }
+
+  #[test]
+  #[ignore = "requires ffmpeg with libx264 and aac"]
+  pub fn test_detect_black_bars() {
+    let folder = TestFolder::new("black-bars");
+    // a gray 320x180 picture, letterboxed to 320x240
+    let inputs = ["-f", "lavfi", "-i", "color=c=gray:size=320x180:rate=25:duration=4"];
+    let output_args = ["-vf", "pad=320:240:0:30:black"];
+    let input_file_path = create_video(&folder, "input.mp4", &inputs, &output_args);
+
+    // the edges of the bars can be blurred by the encoder
+    let dimensions = detect_black_bars(&input_file_path, &CancelToken::new()).unwrap();
+    assert_eq!((dimensions.top_left_point.x, dimensions.size.width), (0, 320));
+    assert!(dimensions.top_left_point.y.abs_diff(30) <= 2);
+    assert!(dimensions.size.height.abs_diff(180) <= 4);
+
+    // the detected crop is applied like a requested one
+    let params = CropParameters {
+      input_file_path,
+      output_file_path: Some(folder.file("output.mp4")),
+      remove_black_bars: Some(true),
+      ..Default::default()
+    };
+    let result = crop_video(&params).unwrap();
+    let output = probe_video(&result.file_path, &CancelToken::new()).unwrap();
+    assert_eq!((output.width, output.height), (320, dimensions.size.height & !1));
   }
 }